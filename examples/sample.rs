@@ -1,47 +1,54 @@
-use deck_analyzer::all_search::{all_search_pattern, AllSearchResult};
-use deck_analyzer::domain::{Card, InitialPattern};
-use deck_analyzer::probability::pattern_prob;
-use deck_analyzer::reader::read_json;
-use indicatif::ProgressStyle;
-
-use std::path::PathBuf;
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let deck = read_json::<Vec<Card>>(&PathBuf::from("sample_deck_v3.json"))?;
-    let init_patterns =
-        read_json::<Vec<InitialPattern>>(&PathBuf::from("sample_init_pattern.json"))?;
-
-    for pat in init_patterns.iter() {
-        let prob = pattern_prob(&deck, pat, 5)?;
-        let InitialPattern { name, level, .. } = pat;
-
-        println!("name: {}, level: {}, prob: {:.8}", name, level, prob);
-    }
-
-    println!("");
-
-    println!("all search");
-
-    println!("pattern prob");
-
-    let AllSearchResult {
-        pattern_result,
-        level_result,
-    } = all_search_pattern(&deck, &init_patterns, 5, ProgressStyle::default_bar())?;
-
-    for (i, pat) in init_patterns.iter().enumerate() {
-        let InitialPattern { name, level, .. } = pat;
-        println!(
-            "name: {}, level: {}, prob: {:.8}",
-            name, level, pattern_result[i]
-        );
-    }
-
-    println!("level prob");
-
-    for i in 0..level_result.len() {
-        println!("level: {}, prob: {:.8}", i, level_result[i]);
-    }
-
-    Ok(())
-}
+use deck_analyzer::all_search::{all_search_pattern, AllSearchResult};
+use deck_analyzer::combinations::BinomialTable;
+use deck_analyzer::domain::{Deck, InitialPattern};
+use deck_analyzer::probability::pattern_prob;
+use deck_analyzer::progress::Progress;
+use deck_analyzer::reader::{read_json, read_patterns};
+
+use std::path::PathBuf;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let deck = read_json::<Deck>(&PathBuf::from("sample_deck_v3.json"))?;
+    let init_patterns = read_patterns(&PathBuf::from("sample_init_pattern.json"))?;
+
+    let table = BinomialTable::new(deck.size() as u64);
+    for pat in init_patterns.iter() {
+        let prob = pattern_prob(&deck, pat, 5, &table)?;
+        let InitialPattern { name, level, .. } = pat;
+
+        println!("name: {}, level: {}, prob: {:.8}", name, level, prob);
+    }
+
+    println!();
+
+    println!("all search");
+
+    println!("pattern prob");
+
+    let AllSearchResult {
+        pattern_result,
+        level_result,
+    } = all_search_pattern(
+        &deck,
+        &init_patterns,
+        5,
+        &mut Progress::new(&mut |done, total| eprint!("\r{done}/{total}")),
+    )?;
+    eprintln!();
+
+    for (i, pat) in init_patterns.iter().enumerate() {
+        let InitialPattern { name, level, .. } = pat;
+        println!(
+            "name: {}, level: {}, prob: {:.8}",
+            name, level, pattern_result[i]
+        );
+    }
+
+    println!("level prob");
+
+    for (i, prob) in level_result.iter().enumerate() {
+        println!("level: {}, prob: {:.8}", i, prob);
+    }
+
+    Ok(())
+}
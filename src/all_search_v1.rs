@@ -1,242 +1,383 @@
-use crate::combinations::combination_n;
-use crate::domain::{Card, InitialPattern};
-use crate::error::AppError;
-
-use indicatif::{ProgressBar, ProgressIterator, ProgressStyle};
-use itertools::Itertools;
-
-// 全探索の結果
-#[derive(Debug, Clone, PartialEq)]
-pub struct AllSearchResult {
-    pub pattern_result: Vec<f64>,
-    pub level_result: Vec<f64>,
-}
-
-/// ドローパターンを全探索
-pub fn all_search_pattern(
-    deck: &Vec<Card>,
-    patterns: &Vec<InitialPattern>,
-    draw_n: u64,
-    pb_style: ProgressStyle,
-) -> Result<AllSearchResult, AppError> {
-    let mut numbers_per_pat: Vec<u64> = vec![0; patterns.len()]; // パターンの場合の数
-    let max_level =
-        patterns
-            .iter()
-            .map(|pat| pat.level)
-            .max()
-            .ok_or(AppError::InvalidDataError(
-                "Invalid init_patterns.".to_string(),
-            ))?;
-    let mut numbers_per_level: Vec<u64> = vec![0; max_level as usize + 1];
-
-    let mut patterns_vec: Vec<(Vec<&String>, Option<Vec<&String>>, Option<Vec<&String>>)> =
-        Vec::new(); // パターンのfirst, second, thirdをそれぞれHashSetにしたもの
-    for pat in patterns.iter() {
-        let InitialPattern {
-            first,
-            second,
-            third,
-            ..
-        } = pat;
-        match (second, third) {
-            // firstのみの場合
-            (None, None) => {
-                patterns_vec.push((first.as_vec(), None, None));
-            }
-            // first, secondのみの場合
-            (Some(second), None) => {
-                patterns_vec.push((first.as_vec(), Some(second.as_vec()), None));
-            }
-            // first, second, thirdの場合
-            (Some(second), Some(third)) => {
-                patterns_vec.push((first.as_vec(), Some(second.as_vec()), Some(third.as_vec())))
-            }
-            (_, _) => {
-                return Err(AppError::InvalidDataError(
-                    "Invalid init_patters".to_string(),
-                ));
-            }
-        }
-    }
-
-    // 枚数を考慮したデッキのカード名の羅列
-    let card_names: Vec<&String> = {
-        let mut card_names = Vec::new();
-        for card in deck.iter() {
-            for _ in 0..card.number {
-                card_names.push(&card.name);
-            }
-        }
-        card_names
-    };
-
-    let all_search_combination = (0..card_names.len()).combinations(draw_n as usize); // 手札パターンの組み合わせ(インデックス)をyieldするイテレータ―
-    let all_pattern_number =
-        combination_n(card_names.len() as u64, draw_n).ok_or(AppError::OverflowCombinationError)?; // all_search_combinationの長さ
-
-    // 全探索のイテレーション(n_h * n_p * n_p_c) (n_h: ハンドの組み合わせ数, n_p: 初動パターン数, パターン内のカード)
-    for hands_indices in all_search_combination
-        .progress_with(ProgressBar::new(all_pattern_number).with_style(pb_style))
-    {
-        let mut hand_names: Vec<&String> = hands_indices
-            .into_iter()
-            .map(|j| *card_names.get(j).unwrap())
-            .collect();
-
-        // 手札のカード名をソート
-        hand_names.sort();
-
-        // ハンドに対応したレベル
-        let mut level_flag: Option<usize> = None;
-
-        for (pat_i, (first_vec, second_vec, third_vec)) in patterns_vec.iter().enumerate() {
-            let level = patterns.get(pat_i).unwrap().level;
-
-            match (second_vec, third_vec) {
-                // firstのみの場合
-                (None, None) => {
-                    let mut pattern_flag = false;
-
-                    for first_card_name in first_vec.into_iter() {
-                        let pattern_names = [*first_card_name];
-                        if lexicographical_superset(&hand_names, &pattern_names) {
-                            pattern_flag = true;
-                            break; // 早期終了
-                        }
-                    }
-
-                    // パターンにマッチした場合
-                    if pattern_flag {
-                        numbers_per_pat[pat_i] += 1;
-                        level_flag = op_max(level_flag, Some(level as usize));
-                    }
-                }
-                // first, secondのみの場合
-                (Some(second_vec), None) => {
-                    let mut pattern_flag = false;
-
-                    for pattern_names in vec![first_vec, second_vec]
-                        .into_iter()
-                        .multi_cartesian_product()
-                    {
-                        let mut pattern_names = pattern_names
-                            .into_iter()
-                            .map(|name| *name)
-                            .collect::<Vec<_>>();
-                        pattern_names.sort();
-
-                        if lexicographical_superset(&hand_names, &pattern_names) {
-                            pattern_flag = true;
-                            break; // 早期終了
-                        }
-                    }
-
-                    // パターンにマッチした場合
-                    if pattern_flag {
-                        numbers_per_pat[pat_i] += 1;
-                        level_flag = op_max(level_flag, Some(level as usize));
-                    }
-                }
-                // first, second, thirdの場合
-                (Some(second_vec), Some(third_vec)) => {
-                    let mut pattern_flag = false;
-
-                    for pattern_names in vec![first_vec, second_vec, third_vec]
-                        .into_iter()
-                        .multi_cartesian_product()
-                    {
-                        let mut pattern_names = pattern_names
-                            .into_iter()
-                            .map(|name| *name)
-                            .collect::<Vec<_>>();
-                        pattern_names.sort();
-                        if lexicographical_superset(&hand_names, &pattern_names) {
-                            pattern_flag = true;
-                            break; // 早期終了
-                        }
-                    }
-
-                    // パターンにマッチした場合
-                    if pattern_flag {
-                        numbers_per_pat[pat_i] += 1;
-                        level_flag = op_max(level_flag, Some(level as usize));
-                    }
-                }
-                (_, _) => {}
-            }
-        }
-
-        if let Some(level) = level_flag {
-            // そのレベルまでの全てのレベルをインクリメント
-            for l in 0..level + 1 {
-                numbers_per_level[l] += 1;
-            }
-        }
-    }
-
-    Ok(AllSearchResult {
-        pattern_result: numbers_per_pat
-            .into_iter()
-            .map(|pat_n| pat_n as f64 / all_pattern_number as f64)
-            .collect(),
-        level_result: numbers_per_level
-            .into_iter()
-            .map(|level_n| level_n as f64 / all_pattern_number as f64)
-            .collect(),
-    })
-}
-
-/// Option<T>の大きい方を取得する
-fn op_max<T: Ord>(x: Option<T>, y: Option<T>) -> Option<T> {
-    match (x, y) {
-        (Some(x), Some(y)) => Some(std::cmp::max(x, y)),
-        (None, Some(y)) => Some(y),
-        (Some(x), None) => Some(x),
-        (None, None) => None,
-    }
-}
-
-/// ソートされた手札とその部分パターンについて，辞書式に比較して手札がスーパーセットであるかどうかを取得する
-fn lexicographical_superset<T: PartialEq>(hands: &[T], pattern: &[T]) -> bool {
-    let hands_iter = hands.iter();
-    let mut pattern_iter = pattern.iter();
-
-    if let Some(mut pattern_card_name) = pattern_iter.next() {
-        for hand_card_name in hands_iter {
-            // 手札の一枚とパターンの一枚が一致する場合
-            if hand_card_name == pattern_card_name {
-                match pattern_iter.next() {
-                    Some(next_pattern_card_name) => {
-                        pattern_card_name = next_pattern_card_name;
-                    }
-                    None => {
-                        // pattern内のカードが全て一致したため
-                        return true;
-                    }
-                }
-            }
-        }
-        // trueが返る前にhands_iterのイテレーションが終了してしまった場合
-        false
-    } else {
-        // パターンにカードが一つも入っていない場合
-        true
-    }
-}
-
-#[cfg(test)]
-mod test {
-    #[test]
-    fn test_lexicographical_superset() {
-        let hands = vec!["A", "B", "C", "D", "E"];
-        let pattern_1 = vec!["C", "D"];
-        assert!(super::lexicographical_superset(&hands, &pattern_1));
-
-        let pattern_2 = vec!["C", "F"];
-        assert!(!super::lexicographical_superset(&hands, &pattern_2));
-
-        let pattern_3 = vec!["C", "B"];
-        assert!(!super::lexicographical_superset(&hands, &pattern_3));
-    }
-}
+use crate::combinations::{combination_n, validate_draw_n};
+use crate::domain::{fill_counts, CardId, Deck, InitialPattern, MulliganMethod, MulliganPolicy};
+use crate::error::AppError;
+use crate::matcher::{MatchHand, PatternMatcher};
+use crate::progress::Progress;
+
+use itertools::Itertools;
+use serde::Serialize;
+
+// 全探索の結果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AllSearchResult {
+    pub pattern_result: Vec<f64>,
+    pub level_result: Vec<f64>,
+}
+
+/// マリガンを含めた全探索の結果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MulliganResult {
+    /// マリガン後のレベルごとの確率
+    pub level_result: Vec<f64>,
+    /// マリガンする確率
+    pub mulligan_rate: f64,
+}
+
+/// 全探索で共通して用いるデッキとパターンの情報．カードは全てIDで表す．
+pub(crate) struct SearchContext {
+    /// コンパイルしたパターン
+    matcher: PatternMatcher,
+    /// 枚数を考慮したデッキのカードIDの羅列
+    card_ids: Vec<CardId>,
+    /// IDごとのデッキ内の枚数
+    deck_numbers: Vec<u8>,
+    /// IDごとの代用カードのサーチ先．代用カードでない場合は空
+    substitutes: Vec<Vec<CardId>>,
+    pub(crate) max_level: u8,
+}
+
+impl SearchContext {
+    pub(crate) fn new(deck: &Deck, patterns: &[InitialPattern]) -> Result<Self, AppError> {
+        let matcher = PatternMatcher::new(deck, patterns)?;
+
+        Ok(Self {
+            max_level: matcher.max_level,
+            matcher,
+            card_ids: deck.card_ids(),
+            deck_numbers: deck.numbers(),
+            substitutes: deck_substitutes(deck)?,
+        })
+    }
+
+    /// IDごとの枚数で表した手札に対応したレベルを取得する．マッチしたパターンのインデックスごとに`on_match`を呼ぶ．
+    pub(crate) fn hand_level(&self, counts: &[u8], on_match: impl FnMut(usize)) -> Option<usize> {
+        // 代用カードをサーチ先に置き換えた手札の候補
+        let virtual_hands = substitute_hands(counts, &self.substitutes, &self.deck_numbers)
+            .into_iter()
+            .map(MatchHand::new)
+            .collect::<Vec<_>>();
+
+        self.matcher.hand_level(&virtual_hands, on_match)
+    }
+
+    /// 手札の組み合わせを全てyieldするイテレーターとその長さ．手札のカードIDはソートされている．
+    fn hands(
+        &self,
+        draw_n: u64,
+    ) -> Result<(impl Iterator<Item = Vec<CardId>> + '_, u64), AppError> {
+        validate_draw_n(self.card_ids.len() as u64, draw_n)?;
+        let all_search_combination = (0..self.card_ids.len()).combinations(draw_n as usize); // 手札パターンの組み合わせ(インデックス)をyieldするイテレータ―
+        let all_pattern_number = combination_n(self.card_ids.len() as u64, draw_n)
+            .ok_or(AppError::OverflowCombinationError)?; // all_search_combinationの長さ
+
+        let hands = all_search_combination.map(|hands_indices| {
+            hands_indices
+                .into_iter()
+                .map(|j| self.card_ids[j])
+                .collect()
+        });
+
+        Ok((hands, all_pattern_number))
+    }
+
+    /// IDごとの枚数の配列の長さ
+    fn type_n(&self) -> usize {
+        self.deck_numbers.len()
+    }
+}
+
+/// レベルに対応したカウンタについて，そのレベルまでの全てのレベルをインクリメント
+pub(crate) fn increment_level(numbers_per_level: &mut [u64], level: usize) {
+    for number in numbers_per_level.iter_mut().take(level + 1) {
+        *number += 1;
+    }
+}
+
+/// ドローパターンを全探索．手札一つごとに`progress`を進める．
+pub fn all_search_pattern(
+    deck: &Deck,
+    patterns: &[InitialPattern],
+    draw_n: u64,
+    progress: &mut Progress,
+) -> Result<AllSearchResult, AppError> {
+    let context = SearchContext::new(deck, patterns)?;
+
+    let mut numbers_per_pat: Vec<u64> = vec![0; patterns.len()]; // パターンの場合の数
+    let mut numbers_per_level: Vec<u64> = vec![0; context.max_level as usize + 1];
+
+    let (hands, all_pattern_number) = context.hands(draw_n)?;
+    progress.start(all_pattern_number)?;
+
+    let mut counts = vec![0; context.type_n()];
+    // 全探索のイテレーション(n_h * n_p * n_p_c) (n_h: ハンドの組み合わせ数, n_p: 初動パターン数, パターン内のカード)
+    for hand_ids in hands {
+        fill_counts(&mut counts, &hand_ids);
+        let level_flag = context.hand_level(&counts, |pat_i| numbers_per_pat[pat_i] += 1);
+
+        if let Some(level) = level_flag {
+            increment_level(&mut numbers_per_level, level);
+        }
+        progress.advance(1)?;
+    }
+    progress.finish();
+
+    Ok(AllSearchResult {
+        pattern_result: numbers_per_pat
+            .into_iter()
+            .map(|pat_n| pat_n as f64 / all_pattern_number as f64)
+            .collect(),
+        level_result: numbers_per_level
+            .into_iter()
+            .map(|level_n| level_n as f64 / all_pattern_number as f64)
+            .collect(),
+    })
+}
+
+/// マリガンを含めてドローパターンを全探索する．引き直した手札は最初の手札と独立であるため，
+/// 最初の手札の全探索の中で引き直した場合のレベルも同時に数え上げる．
+pub fn all_search_mulligan(
+    deck: &Deck,
+    patterns: &[InitialPattern],
+    draw_n: u64,
+    policy: &MulliganPolicy,
+    progress: &mut Progress,
+) -> Result<MulliganResult, AppError> {
+    let context = SearchContext::new(deck, patterns)?;
+
+    let keep_n = match policy.method {
+        MulliganMethod::ShuffleBack => draw_n,
+        MulliganMethod::London { bottom_n } => {
+            draw_n
+                .checked_sub(bottom_n as u64)
+                .ok_or(AppError::InvalidDataError(
+                    "bottom_n is larger than draw_n.".to_string(),
+                ))?
+        }
+    }; // 引き直した後に残す枚数
+
+    let mut keep_numbers_per_level: Vec<u64> = vec![0; context.max_level as usize + 1]; // 最初の手札を残す場合のレベルの場合の数
+    let mut redraw_numbers_per_level: Vec<u64> = vec![0; context.max_level as usize + 1]; // 引き直した手札のレベルの場合の数
+    let mut mulligan_number: u64 = 0;
+
+    let (hands, all_pattern_number) = context.hands(draw_n)?;
+    progress.start(all_pattern_number)?;
+
+    let mut counts = vec![0; context.type_n()];
+    for hand_ids in hands {
+        fill_counts(&mut counts, &hand_ids);
+        let level_flag = context.hand_level(&counts, |_| {});
+
+        match level_flag {
+            Some(level) if level >= policy.level as usize => {
+                increment_level(&mut keep_numbers_per_level, level);
+            }
+            _ => {
+                mulligan_number += 1;
+            }
+        }
+
+        // 引き直した手札として扱う場合のレベル
+        let redraw_level_flag = if keep_n == draw_n {
+            level_flag
+        } else {
+            // 残す手札の中で最もレベルの高いもの
+            hand_ids
+                .iter()
+                .copied()
+                .combinations(keep_n as usize)
+                .map(|keep_ids| {
+                    fill_counts(&mut counts, &keep_ids);
+                    context.hand_level(&counts, |_| {})
+                })
+                .max()
+                .flatten()
+        };
+
+        if let Some(level) = redraw_level_flag {
+            increment_level(&mut redraw_numbers_per_level, level);
+        }
+        progress.advance(1)?;
+    }
+    progress.finish();
+
+    let all_pattern_number = all_pattern_number as f64;
+    let mulligan_rate = mulligan_number as f64 / all_pattern_number;
+
+    Ok(MulliganResult {
+        level_result: keep_numbers_per_level
+            .into_iter()
+            .zip(redraw_numbers_per_level)
+            .map(|(keep_n, redraw_n)| {
+                keep_n as f64 / all_pattern_number
+                    + mulligan_rate * redraw_n as f64 / all_pattern_number
+            })
+            .collect(),
+        mulligan_rate,
+    })
+}
+
+/// IDごとの代用カードのサーチ先．代用カードでない場合は空．`acts_as`に不正なクエリがある場合はエラー
+pub(crate) fn deck_substitutes(deck: &Deck) -> Result<Vec<Vec<CardId>>, AppError> {
+    deck.iter()
+        .map(|card| {
+            card.acts_as
+                .as_ref()
+                .map_or_else(|| Ok(Vec::new()), |acts_as| deck.ids_of(acts_as))
+        })
+        .collect()
+}
+
+/// 手札の代用カードを，デッキに残っているサーチ先のいずれかに置き換えた手札の候補を全て取得する．
+/// 置き換えない場合も含む．サーチ先が全て手札にあるか他の代用カードで使われている場合は置き換えない．
+/// 手札と各候補はIDごとの枚数で表す．`deck_numbers`は手札とデッキに残っているカードを合わせたIDごとの枚数
+pub(crate) fn substitute_hands(
+    counts: &[u8],
+    substitutes: &[Vec<CardId>],
+    deck_numbers: &[u8],
+) -> Vec<Vec<u8>> {
+    let mut virtual_hands = vec![counts.to_vec()];
+
+    for (i, targets) in substitutes.iter().enumerate() {
+        if targets.is_empty() {
+            continue;
+        }
+
+        // 代用カード一枚ごとに置き換える
+        for _ in 0..counts[i] {
+            let mut next_virtual_hands = Vec::new();
+            for virtual_hand in virtual_hands.into_iter() {
+                for target in targets.iter() {
+                    // デッキにサーチ先が残っている場合
+                    let j = target.index();
+                    if deck_numbers[j] > virtual_hand[j] {
+                        let mut next_virtual_hand = virtual_hand.clone();
+                        next_virtual_hand[i] -= 1;
+                        next_virtual_hand[j] += 1;
+                        next_virtual_hands.push(next_virtual_hand);
+                    }
+                }
+                next_virtual_hands.push(virtual_hand);
+            }
+            virtual_hands = next_virtual_hands;
+        }
+    }
+
+    if virtual_hands.len() > 1 {
+        virtual_hands.sort();
+        virtual_hands.dedup();
+    }
+
+    virtual_hands
+}
+
+#[cfg(test)]
+mod test {
+    use super::{all_search_mulligan, all_search_pattern};
+    use crate::domain::{Card, Deck, InitialPattern, MulliganMethod, MulliganPolicy, StringOrList};
+    use crate::progress::Progress;
+
+    #[test]
+    fn test_all_search_acts_as() {
+        let deck = Deck::new(vec![
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 1,
+                ..Default::default()
+            },
+            Card {
+                name: "溟界の蛇睡蓮".to_string(),
+                number: 1,
+                acts_as: Some(StringOrList::String("溟界の滓－ヌル".to_string())),
+                ..Default::default()
+            },
+            Card {
+                name: "灰流うらら".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+
+        let patterns = vec![
+            InitialPattern {
+                name: "ヌル".to_string(),
+                first: StringOrList::String("溟界の滓－ヌル".to_string()),
+                second: None,
+                third: None,
+                level: 1,
+                extra: None,
+            },
+            InitialPattern {
+                name: "ヌル＋蛇睡蓮".to_string(),
+                first: StringOrList::String("溟界の滓－ヌル".to_string()),
+                second: Some(StringOrList::String("溟界の蛇睡蓮".to_string())),
+                third: None,
+                level: 1,
+                extra: None,
+            },
+            InitialPattern {
+                name: "ヌル＋ヌル".to_string(),
+                first: StringOrList::String("溟界の滓－ヌル".to_string()),
+                second: Some(StringOrList::String("溟界の滓－ヌル".to_string())),
+                third: None,
+                level: 2,
+                extra: None,
+            },
+        ];
+
+        let result = all_search_pattern(&deck, &patterns, 2, &mut Progress::silent()).unwrap();
+
+        // 10通りのうち，ヌルを含む4通りと蛇睡蓮がヌルとして扱われる3通り
+        assert_eq!(result.pattern_result[0], 0.7);
+        assert_eq!(result.pattern_result[1], 0.1);
+        // ヌルが手札にある場合，蛇睡蓮はヌルとして扱えない
+        assert_eq!(result.pattern_result[2], 0.0);
+    }
+
+    #[test]
+    fn test_all_search_mulligan() {
+        let deck = Deck::new(vec![
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 1,
+                ..Default::default()
+            },
+            Card {
+                name: "灰流うらら".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+
+        let patterns = vec![InitialPattern {
+            name: "ヌル".to_string(),
+            first: StringOrList::String("溟界の滓－ヌル".to_string()),
+            second: None,
+            third: None,
+            level: 1,
+            extra: None,
+        }];
+
+        let shuffle_back = MulliganPolicy {
+            level: 1,
+            method: MulliganMethod::ShuffleBack,
+        };
+        let result =
+            all_search_mulligan(&deck, &patterns, 1, &shuffle_back, &mut Progress::silent())
+                .unwrap();
+
+        assert_eq!(result.mulligan_rate, 0.75);
+        assert_eq!(result.level_result, vec![0.4375, 0.4375]); // 0.25 + 0.75 * 0.25
+
+        let london = MulliganPolicy {
+            level: 1,
+            method: MulliganMethod::London { bottom_n: 1 },
+        };
+        let result =
+            all_search_mulligan(&deck, &patterns, 2, &london, &mut Progress::silent()).unwrap();
+
+        assert_eq!(result.mulligan_rate, 0.5);
+        assert_eq!(result.level_result, vec![0.75, 0.75]); // 0.5 + 0.5 * 0.5
+    }
+}
@@ -0,0 +1,507 @@
+use crate::all_search_v1::{AllSearchResult, SearchContext};
+use crate::combinations::{combination_big, validate_draw_n, BinomialTable};
+use crate::domain::{validate_patterns, Card, Deck, InitialPattern};
+use crate::error::AppError;
+use crate::progress::Progress;
+
+use num_bigint::{BigInt, BigUint};
+use num_rational::BigRational;
+use num_traits::{ToPrimitive, Zero};
+use std::collections::HashMap;
+
+/// カードの種類(カード名)ごとの枚数で表した手札
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandType {
+    /// カードの種類ごとの枚数．`CardTypes::names`と同じ順番で，`Deck`から作成した場合は`CardId`の順番と一致する．
+    pub numbers: Vec<u8>,
+    /// この手札になる引き方の場合の数
+    pub weight: u128,
+}
+
+/// デッキのカードの種類とその枚数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardTypes<'a> {
+    /// 重複の無いカード名
+    pub names: Vec<&'a String>,
+    /// カードの種類ごとのデッキ内の枚数
+    pub numbers: Vec<u8>,
+}
+
+impl<'a> CardTypes<'a> {
+    /// 同じ名前のカードはまとめる．
+    pub fn new(deck: &'a [Card]) -> Self {
+        let mut names: Vec<&String> = Vec::new();
+        let mut numbers: Vec<u8> = Vec::new();
+        let mut indices: HashMap<&String, usize> = HashMap::new();
+
+        for card in deck.iter() {
+            match indices.get(&card.name) {
+                Some(i) => numbers[*i] += card.number,
+                None => {
+                    indices.insert(&card.name, names.len());
+                    names.push(&card.name);
+                    numbers.push(card.number);
+                }
+            }
+        }
+
+        Self { names, numbers }
+    }
+
+    /// デッキの枚数
+    pub fn card_n(&self) -> u64 {
+        self.numbers.iter().fold(0_u64, |acc, n| acc + *n as u64)
+    }
+
+    /// `draw_n`枚引いた場合の手札を種類ごとの枚数で全て列挙する．場合の数は`table`から求める．
+    pub fn hand_types(
+        &self,
+        draw_n: u64,
+        table: &BinomialTable,
+    ) -> Result<Vec<HandType>, AppError> {
+        self.hand_numbers(draw_n)
+            .into_iter()
+            .map(|numbers| {
+                Ok(HandType {
+                    weight: self.weight(&numbers, table)?,
+                    numbers,
+                })
+            })
+            .collect()
+    }
+
+    /// `draw_n`枚引いた場合の手札の種類ごとの枚数を全て列挙する．
+    pub fn hand_numbers(&self, draw_n: u64) -> Vec<Vec<u8>> {
+        let mut hand_numbers = Vec::new();
+        let mut numbers = vec![0_u8; self.numbers.len()];
+        self.push_hand_numbers(0, draw_n, &mut numbers, &mut hand_numbers);
+        hand_numbers
+    }
+
+    /// `type_i`番目以降の種類から`rest_n`枚引く場合を再帰的に列挙する．
+    fn push_hand_numbers(
+        &self,
+        type_i: usize,
+        rest_n: u64,
+        numbers: &mut Vec<u8>,
+        hand_numbers: &mut Vec<Vec<u8>>,
+    ) {
+        if rest_n == 0 {
+            hand_numbers.push(numbers.clone());
+            return;
+        }
+        if type_i == self.numbers.len() {
+            // 引き切れない場合
+            return;
+        }
+
+        let max_n = std::cmp::min(self.numbers[type_i] as u64, rest_n);
+        for n in 0..=max_n {
+            numbers[type_i] = n as u8;
+            self.push_hand_numbers(type_i + 1, rest_n - n, numbers, hand_numbers);
+        }
+        numbers[type_i] = 0;
+    }
+
+    /// 種類ごとの枚数が`numbers`となる引き方の場合の数．
+    fn weight(&self, numbers: &[u8], table: &BinomialTable) -> Result<u128, AppError> {
+        self.numbers
+            .iter()
+            .zip(numbers.iter())
+            .try_fold(1_u128, |acc, (deck_n, n)| {
+                table
+                    .get(*deck_n as u64, *n as u64)?
+                    .checked_mul(acc)
+                    .ok_or(AppError::OverflowCombinationError)
+            })
+    }
+
+    /// 種類ごとの枚数が`numbers`となる引き方の場合の数を多倍長整数で求める．
+    pub fn exact_weight(&self, numbers: &[u8]) -> BigUint {
+        self.numbers
+            .iter()
+            .zip(numbers.iter())
+            .map(|(deck_n, n)| combination_big(*deck_n as u64, *n as u64))
+            .product()
+    }
+
+    /// 種類ごとの枚数で表した手札をソートされたカード名の羅列に変換する．
+    pub fn hand_names(&self, numbers: &[u8]) -> Vec<&'a String> {
+        let mut hand_names = Vec::new();
+        for (name, n) in self.names.iter().zip(numbers.iter()) {
+            for _ in 0..*n {
+                hand_names.push(*name);
+            }
+        }
+        hand_names.sort();
+        hand_names
+    }
+}
+
+/// カードの種類ごとの枚数で手札を全探索し，手札ごとにマッチしたパターンとレベルを`visit`に渡す．
+/// 全ての引き方の場合の数を返す．`table`はデッキの枚数以上まで計算されている必要がある．
+/// 手札の種類一つごとに`progress`を進める．
+pub fn search_hand_types(
+    deck: &Deck,
+    patterns: &[InitialPattern],
+    draw_n: u64,
+    table: &BinomialTable,
+    progress: &mut Progress,
+    mut visit: impl FnMut(&HandType, &[bool], Option<usize>),
+) -> Result<u128, AppError> {
+    let context = SearchContext::new(deck, patterns)?;
+    let card_types = CardTypes::new(deck);
+    validate_draw_n(card_types.card_n(), draw_n)?;
+
+    let all_pattern_number = table.get(card_types.card_n(), draw_n)?;
+    let hand_types = card_types.hand_types(draw_n, table)?;
+    progress.start(hand_types.len() as u64)?;
+
+    let mut matched = vec![false; patterns.len()];
+    for hand_type in hand_types.iter() {
+        matched.fill(false);
+
+        let level_flag = context.hand_level(&hand_type.numbers, |pat_i| matched[pat_i] = true);
+
+        visit(hand_type, &matched, level_flag);
+        progress.advance(1)?;
+    }
+    progress.finish();
+
+    Ok(all_pattern_number)
+}
+
+/// カードの種類ごとの枚数で手札を全探索する．同じ種類のカードの引き方をまとめて数えるため，
+/// `all_search_v1`より高速．場合の数は分析ごとに一度だけ作成した`table`から求める．手札の種類一つごとに`progress`を進める．
+pub fn all_search_pattern(
+    deck: &Deck,
+    patterns: &[InitialPattern],
+    draw_n: u64,
+    table: &BinomialTable,
+    progress: &mut Progress,
+) -> Result<AllSearchResult, AppError> {
+    let max_level = validate_patterns(patterns)?;
+
+    let mut numbers_per_pat: Vec<u128> = vec![0; patterns.len()]; // パターンの場合の数
+    let mut numbers_per_level: Vec<u128> = vec![0; max_level as usize + 1];
+
+    let all_pattern_number = search_hand_types(
+        deck,
+        patterns,
+        draw_n,
+        table,
+        progress,
+        |hand_type, matched, level_flag| {
+            for (number, _) in numbers_per_pat
+                .iter_mut()
+                .zip(matched.iter())
+                .filter(|(_, matched)| **matched)
+            {
+                *number += hand_type.weight;
+            }
+
+            if let Some(level) = level_flag {
+                for number in numbers_per_level.iter_mut().take(level + 1) {
+                    *number += hand_type.weight;
+                }
+            }
+        },
+    )?;
+
+    Ok(AllSearchResult {
+        pattern_result: numbers_per_pat
+            .into_iter()
+            .map(|pat_n| pat_n as f64 / all_pattern_number as f64)
+            .collect(),
+        level_result: numbers_per_level
+            .into_iter()
+            .map(|level_n| level_n as f64 / all_pattern_number as f64)
+            .collect(),
+    })
+}
+
+/// 多倍長の有理数による全探索の結果．誤差やオーバーフローが無い．
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExactSearchResult {
+    pub pattern_result: Vec<BigRational>,
+    pub level_result: Vec<BigRational>,
+}
+
+impl ExactSearchResult {
+    /// 浮動小数点数の結果に変換する．
+    pub fn to_f64(&self) -> AllSearchResult {
+        let to_f64 = |probs: &[BigRational]| -> Vec<f64> {
+            probs
+                .iter()
+                .map(|prob| prob.to_f64().unwrap_or(f64::NAN))
+                .collect()
+        };
+
+        AllSearchResult {
+            pattern_result: to_f64(&self.pattern_result),
+            level_result: to_f64(&self.level_result),
+        }
+    }
+}
+
+/// `all_search_pattern`と同様に全探索し，場合の数を多倍長整数で数えて確率を既約分数で求める．
+/// 大きなデッキやドロー数でもオーバーフローしない．手札の種類一つごとに`progress`を進める．
+pub fn all_search_pattern_exact(
+    deck: &Deck,
+    patterns: &[InitialPattern],
+    draw_n: u64,
+    progress: &mut Progress,
+) -> Result<ExactSearchResult, AppError> {
+    let context = SearchContext::new(deck, patterns)?;
+    let card_types = CardTypes::new(deck);
+    validate_draw_n(card_types.card_n(), draw_n)?;
+
+    let mut numbers_per_pat: Vec<BigUint> = vec![BigUint::zero(); patterns.len()];
+    let mut numbers_per_level: Vec<BigUint> = vec![BigUint::zero(); context.max_level as usize + 1];
+
+    let hand_numbers = card_types.hand_numbers(draw_n);
+    progress.start(hand_numbers.len() as u64)?;
+    for numbers in hand_numbers.iter() {
+        let weight = card_types.exact_weight(numbers);
+
+        let level_flag = context.hand_level(numbers, |pat_i| {
+            numbers_per_pat[pat_i] += &weight;
+        });
+        if let Some(level) = level_flag {
+            for number in numbers_per_level.iter_mut().take(level + 1) {
+                *number += &weight;
+            }
+        }
+        progress.advance(1)?;
+    }
+    progress.finish();
+
+    let all_pattern_number = BigInt::from(combination_big(card_types.card_n(), draw_n));
+    let to_prob = |number: BigUint| BigRational::new(number.into(), all_pattern_number.clone());
+
+    Ok(ExactSearchResult {
+        pattern_result: numbers_per_pat.into_iter().map(to_prob).collect(),
+        level_result: numbers_per_level.into_iter().map(to_prob).collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{all_search_pattern, all_search_pattern_exact, CardTypes};
+    use crate::all_search_v1;
+    use crate::combinations::BinomialTable;
+    use crate::domain::{Card, Deck, InitialPattern, StringOrList};
+    use crate::progress::{CancellationToken, Progress};
+    use crate::AppError;
+
+    use num_rational::BigRational;
+
+    #[test]
+    fn test_hand_types() {
+        let deck = Deck::new(vec![
+            Card {
+                name: "A".to_string(),
+                number: 2,
+                ..Default::default()
+            },
+            Card {
+                name: "B".to_string(),
+                number: 1,
+                ..Default::default()
+            },
+            Card {
+                name: "A".to_string(),
+                number: 1,
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+
+        let card_types = CardTypes::new(&deck);
+        assert_eq!(card_types.numbers, vec![3, 1]);
+
+        let hand_types = card_types.hand_types(2, &BinomialTable::new(4)).unwrap();
+        assert_eq!(hand_types.len(), 2); // AA, AB
+        assert_eq!(
+            hand_types.iter().map(|hand| hand.weight).sum::<u128>(),
+            6 // 4C2
+        );
+    }
+
+    #[test]
+    fn test_all_search_v2() {
+        let deck = Deck::new(vec![
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "溟界の滓－ナイア".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "溟界の蛇睡蓮".to_string(),
+                number: 2,
+                acts_as: Some(StringOrList::String("溟界の滓－ヌル".to_string())),
+                ..Default::default()
+            },
+            Card {
+                name: "灰流うらら".to_string(),
+                number: 12,
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+
+        let patterns = vec![
+            InitialPattern {
+                name: "ヌル".to_string(),
+                first: StringOrList::String("溟界の滓－ヌル".to_string()),
+                second: None,
+                third: None,
+                level: 1,
+                extra: None,
+            },
+            InitialPattern {
+                name: "ヌル＋爬虫類".to_string(),
+                first: StringOrList::String("溟界の滓－ヌル".to_string()),
+                second: Some(StringOrList::List(vec![
+                    "溟界の滓－ヌル".to_string(),
+                    "溟界の滓－ナイア".to_string(),
+                ])),
+                third: None,
+                level: 2,
+                extra: None,
+            },
+        ];
+
+        let v1 = all_search_v1::all_search_pattern(&deck, &patterns, 5, &mut Progress::silent())
+            .unwrap();
+        let v2 = all_search_pattern(
+            &deck,
+            &patterns,
+            5,
+            &BinomialTable::new(deck.size() as u64),
+            &mut Progress::silent(),
+        )
+        .unwrap();
+
+        for (p1, p2) in v1
+            .pattern_result
+            .iter()
+            .chain(v1.level_result.iter())
+            .zip(v2.pattern_result.iter().chain(v2.level_result.iter()))
+        {
+            assert!((p1 - p2).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_all_search_exact() {
+        let deck = Deck::new(vec![
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "灰流うらら".to_string(),
+                number: 57,
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+
+        let patterns = vec![InitialPattern {
+            name: "ヌル".to_string(),
+            first: StringOrList::String("溟界の滓－ヌル".to_string()),
+            second: None,
+            third: None,
+            level: 1,
+            extra: None,
+        }];
+
+        let exact = all_search_pattern_exact(&deck, &patterns, 7, &mut Progress::silent()).unwrap();
+        // 1 - 57C7 / 60C7 = 1 - 264385836 / 386206920
+        assert_eq!(
+            exact.pattern_result[0],
+            BigRational::new(121821084.into(), 386206920.into())
+        );
+        assert_eq!(exact.level_result[1], exact.pattern_result[0]);
+
+        let v2 = all_search_pattern(
+            &deck,
+            &patterns,
+            7,
+            &BinomialTable::new(deck.size() as u64),
+            &mut Progress::silent(),
+        )
+        .unwrap();
+        for (p1, p2) in exact
+            .to_f64()
+            .pattern_result
+            .iter()
+            .zip(v2.pattern_result.iter())
+        {
+            assert!((p1 - p2).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_all_search_progress() {
+        let deck = Deck::new(vec![
+            Card {
+                name: "A".to_string(),
+                number: 2,
+                ..Default::default()
+            },
+            Card {
+                name: "B".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+        let patterns = vec![InitialPattern {
+            name: "A".to_string(),
+            first: StringOrList::String("A".to_string()),
+            second: None,
+            third: None,
+            level: 1,
+            extra: None,
+        }];
+
+        // 2枚引く手札の種類はAA, AB, BBの3種類．最後の通知は`finish`による
+        let mut calls = Vec::new();
+        let mut observer = |done: u64, total: u64| calls.push((done, total));
+        all_search_pattern(
+            &deck,
+            &patterns,
+            2,
+            &BinomialTable::new(deck.size() as u64),
+            &mut Progress::new(&mut observer),
+        )
+        .unwrap();
+        assert_eq!(calls, vec![(0, 3), (1, 3), (2, 3), (3, 3), (3, 3)]);
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut progress = Progress::silent().with_token(token);
+        assert!(matches!(
+            all_search_pattern(
+                &deck,
+                &patterns,
+                2,
+                &BinomialTable::new(deck.size() as u64),
+                &mut progress
+            ),
+            Err(AppError::CancelledError)
+        ));
+        assert!(matches!(
+            all_search_pattern_exact(&deck, &patterns, 2, &mut progress),
+            Err(AppError::CancelledError)
+        ));
+    }
+}
@@ -0,0 +1,50 @@
+mod brick;
+mod compare;
+mod interruption;
+mod matchup;
+mod optimizer;
+mod sensitivity;
+mod sweep;
+
+pub use brick::{brick_search, BrickGroup, BrickHand, BrickResult};
+pub use compare::{card_diff, compare_decks, CardDiff, CompareResult, ProbDelta};
+pub use interruption::{interruption_search, InterruptionResult};
+pub use matchup::{matchup_search, MatchupRow, MatchupTable};
+pub use optimizer::{
+    optimize_deck, Annealing, Candidate, Objective, OptimizedDeck, OptimizerSetting,
+};
+pub use sensitivity::{sensitivity_search, CardSensitivity, SensitivityResult, BLANK_CARD_NAME};
+pub use sweep::{sweep_search, Filler, SweepPoint};
+
+/// 分析のテストで共通して用いるデッキとパターン
+#[cfg(test)]
+mod test_util {
+    use crate::domain::{Card, Deck, InitialPattern, StringOrList};
+
+    /// カード名と枚数の組からデッキを作成する．
+    pub(super) fn deck(cards: &[(&str, u8)]) -> Deck {
+        Deck::new(
+            cards
+                .iter()
+                .map(|(name, number)| Card {
+                    name: name.to_string(),
+                    number: *number,
+                    ..Default::default()
+                })
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    /// `name`を一枚引けばよいレベル1のパターン
+    pub(super) fn single_pattern(name: &str) -> InitialPattern {
+        InitialPattern {
+            name: name.to_string(),
+            first: StringOrList::String(name.to_string()),
+            second: None,
+            third: None,
+            level: 1,
+            extra: None,
+        }
+    }
+}
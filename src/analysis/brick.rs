@@ -0,0 +1,258 @@
+use crate::all_search_v2::{search_hand_types, CardTypes};
+use crate::combinations::BinomialTable;
+use crate::domain::{CardGroup, Deck, InitialPattern};
+use crate::progress::Progress;
+use crate::AppError;
+
+use std::collections::{HashMap, HashSet};
+
+/// どのパターンにもマッチしない手札
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrickHand {
+    /// 手札のカード名と枚数
+    pub cards: Vec<(String, u8)>,
+    /// この手札になる確率
+    pub prob: f64,
+}
+
+/// カードのまとまりごとの枚数でまとめた事故手札
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrickGroup {
+    /// カードのまとまりごとの枚数．`groups`と同じ順番
+    pub numbers: Vec<u8>,
+    /// この枚数の組み合わせの事故手札になる確率
+    pub prob: f64,
+    /// 事故手札の中でこの枚数の組み合わせが占める割合．事故手札の確率が0の場合は0
+    pub rate: f64,
+}
+
+/// 事故手札の分析結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrickResult {
+    /// どのパターンにもマッチしない確率
+    pub brick_prob: f64,
+    /// 事故手札．確率の高い順
+    pub hands: Vec<BrickHand>,
+    /// カード名と，事故手札の中でそのカードを含む割合．割合の高い順．事故手札が無い場合は空
+    pub card_rates: Vec<(String, f64)>,
+}
+
+impl BrickResult {
+    /// 事故手札をカードのまとまりごとの枚数でまとめる．確率の高い順．
+    /// まとまりのクエリは`deck`で解決し，不正なクエリの場合はエラー
+    pub fn group_by(&self, deck: &Deck, groups: &[CardGroup]) -> Result<Vec<BrickGroup>, AppError> {
+        let groups_set = groups
+            .iter()
+            .map(|group| Ok(deck.resolve(&group.cards)?.into_iter().collect()))
+            .collect::<Result<Vec<HashSet<&String>>, AppError>>()?;
+
+        let mut probs: HashMap<Vec<u8>, f64> = HashMap::new();
+        for hand in self.hands.iter() {
+            let numbers = groups_set
+                .iter()
+                .map(|group_set| {
+                    hand.cards
+                        .iter()
+                        .filter(|(name, _)| group_set.contains(&name))
+                        .fold(0_u8, |acc, (_, n)| acc + n)
+                })
+                .collect::<Vec<_>>();
+
+            *probs.entry(numbers).or_insert(0.0) += hand.prob;
+        }
+
+        let mut brick_groups = probs
+            .into_iter()
+            .map(|(numbers, prob)| BrickGroup {
+                numbers,
+                prob,
+                rate: if self.brick_prob > 0.0 {
+                    prob / self.brick_prob
+                } else {
+                    0.0
+                },
+            })
+            .collect::<Vec<_>>();
+        brick_groups.sort_by(|x, y| y.prob.total_cmp(&x.prob));
+
+        Ok(brick_groups)
+    }
+}
+
+/// どのパターンにもマッチしない手札を全探索で集め，確率の高い順に並べる．手札の種類一つごとに`progress`を進める．
+pub fn brick_search(
+    deck: &Deck,
+    patterns: &[InitialPattern],
+    draw_n: u64,
+    progress: &mut Progress,
+) -> Result<BrickResult, AppError> {
+    let card_types = CardTypes::new(deck);
+    let table = BinomialTable::new(card_types.card_n());
+
+    let mut brick_hands = Vec::new(); // (手札, 場合の数)
+    let all_pattern_number = search_hand_types(
+        deck,
+        patterns,
+        draw_n,
+        &table,
+        progress,
+        |hand_type, _, level_flag| {
+            if level_flag.is_none() {
+                brick_hands.push(hand_type.clone());
+            }
+        },
+    )?;
+
+    let brick_number = brick_hands
+        .iter()
+        .fold(0_u128, |acc, hand_type| acc + hand_type.weight);
+
+    // カードの種類ごとの事故手札に含まれる場合の数
+    let mut numbers_per_type: Vec<u128> = vec![0; card_types.names.len()];
+    for hand_type in brick_hands.iter() {
+        for (number, _) in numbers_per_type
+            .iter_mut()
+            .zip(hand_type.numbers.iter())
+            .filter(|(_, n)| **n > 0)
+        {
+            *number += hand_type.weight;
+        }
+    }
+
+    brick_hands.sort_by_key(|hand_type| std::cmp::Reverse(hand_type.weight));
+
+    let hands = brick_hands
+        .into_iter()
+        .map(|hand_type| BrickHand {
+            cards: card_types
+                .names
+                .iter()
+                .zip(hand_type.numbers.iter())
+                .filter(|(_, n)| **n > 0)
+                .map(|(name, n)| (name.to_string(), *n))
+                .collect(),
+            prob: hand_type.weight as f64 / all_pattern_number as f64,
+        })
+        .collect();
+
+    // 事故手札が無い場合は割合を求められないため空にする
+    let mut card_rates = match brick_number {
+        0 => Vec::new(),
+        _ => card_types
+            .names
+            .iter()
+            .zip(numbers_per_type)
+            .map(|(name, n)| (name.to_string(), n as f64 / brick_number as f64))
+            .collect::<Vec<_>>(),
+    };
+    card_rates.sort_by(|(_, x), (_, y)| y.total_cmp(x));
+
+    Ok(BrickResult {
+        brick_prob: brick_number as f64 / all_pattern_number as f64,
+        hands,
+        card_rates,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::brick_search;
+    use crate::analysis::test_util::{deck, single_pattern};
+    use crate::domain::{Card, CardGroup, CardMeta, StringOrList};
+    use crate::progress::Progress;
+
+    #[test]
+    fn test_brick_search() {
+        let deck = deck(&[("溟界の滓－ヌル", 1), ("灰流うらら", 1), ("増殖するG", 2)]);
+        let patterns = vec![single_pattern("溟界の滓－ヌル")];
+
+        let result = brick_search(&deck, &patterns, 2, &mut Progress::silent()).unwrap();
+
+        // 6通りのうちヌルを含まない3通り
+        assert_eq!(result.brick_prob, 0.5);
+        assert_eq!(
+            result.hands[0].cards,
+            vec![("灰流うらら".to_string(), 1), ("増殖するG".to_string(), 1)]
+        );
+        assert_eq!(result.hands[0].prob, 2.0 / 6.0);
+        assert_eq!(result.card_rates[0], ("増殖するG".to_string(), 1.0));
+
+        let groups = result
+            .group_by(
+                &deck,
+                &[CardGroup {
+                    name: "手札誘発".to_string(),
+                    cards: StringOrList::List(vec![
+                        "灰流うらら".to_string(),
+                        "増殖するG".to_string(),
+                    ]),
+                }],
+            )
+            .unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].numbers, vec![2]);
+        assert_eq!(groups[0].rate, 1.0);
+    }
+
+    #[test]
+    fn test_group_by_query() {
+        // 手札誘発のタグを持つカードをクエリでまとめる
+        let base_deck = deck(&[("溟界の滓－ヌル", 1), ("灰流うらら", 1), ("増殖するG", 2)]);
+        let deck = base_deck
+            .with_cards(
+                base_deck
+                    .iter()
+                    .map(|card| Card {
+                        meta: CardMeta {
+                            tags: match card.name.as_str() {
+                                "溟界の滓－ヌル" => Vec::new(),
+                                _ => vec!["手札誘発".to_string()],
+                            },
+                            ..Default::default()
+                        },
+                        ..card.clone()
+                    })
+                    .collect(),
+            )
+            .unwrap();
+        let patterns = vec![single_pattern("溟界の滓－ヌル")];
+        let result = brick_search(&deck, &patterns, 2, &mut Progress::silent()).unwrap();
+
+        let groups = result
+            .group_by(
+                &deck,
+                &[CardGroup {
+                    name: "手札誘発".to_string(),
+                    cards: StringOrList::String("@tag=手札誘発".to_string()),
+                }],
+            )
+            .unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].numbers, vec![2]);
+        assert_eq!(groups[0].rate, 1.0);
+
+        // 不正なクエリ
+        assert!(result
+            .group_by(
+                &deck,
+                &[CardGroup {
+                    name: "不正".to_string(),
+                    cards: StringOrList::String("@unknown=1".to_string()),
+                }],
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_brick_search_without_bricks() {
+        // 全ての手札がパターンにマッチする場合
+        let deck = deck(&[("溟界の滓－ヌル", 3), ("灰流うらら", 1)]);
+        let patterns = vec![single_pattern("溟界の滓－ヌル")];
+
+        let result = brick_search(&deck, &patterns, 2, &mut Progress::silent()).unwrap();
+        assert_eq!(result.brick_prob, 0.0);
+        assert!(result.hands.is_empty());
+        assert!(result.card_rates.is_empty());
+        assert!(result.group_by(&deck, &[]).unwrap().is_empty());
+    }
+}
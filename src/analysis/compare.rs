@@ -0,0 +1,201 @@
+use crate::all_search::AllSearchResult;
+use crate::all_search_v2::all_search_pattern;
+use crate::combinations::BinomialTable;
+use crate::domain::{Deck, InitialPattern};
+use crate::progress::Progress;
+use crate::AppError;
+
+/// 二つのデッキ間で枚数の異なるカード
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardDiff {
+    pub name: String,
+    /// エクストラデッキのカードの場合はtrue
+    pub extra: bool,
+    /// 比較元のデッキでの枚数
+    pub old_number: u8,
+    /// 比較先のデッキでの枚数
+    pub new_number: u8,
+}
+
+/// 二つのデッキの比較結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompareResult {
+    pub card_diff: Vec<CardDiff>,
+    pub old_result: AllSearchResult,
+    pub new_result: AllSearchResult,
+}
+
+/// 比較元と比較先の確率とその差分
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProbDelta {
+    pub old_prob: f64,
+    pub new_prob: f64,
+    /// 絶対差分(`new_prob` - `old_prob`)
+    pub abs_delta: f64,
+    /// 相対差分(`abs_delta` / `old_prob`)．`old_prob`が0の場合はNone
+    pub rel_delta: Option<f64>,
+}
+
+impl ProbDelta {
+    pub(super) fn new(old_prob: f64, new_prob: f64) -> Self {
+        let abs_delta = new_prob - old_prob;
+        Self {
+            old_prob,
+            new_prob,
+            abs_delta,
+            rel_delta: (old_prob != 0.0).then(|| abs_delta / old_prob),
+        }
+    }
+}
+
+impl CompareResult {
+    /// パターンごとの確率の差分
+    pub fn pattern_deltas(&self) -> Vec<ProbDelta> {
+        self.old_result
+            .pattern_result
+            .iter()
+            .zip(self.new_result.pattern_result.iter())
+            .map(|(old_prob, new_prob)| ProbDelta::new(*old_prob, *new_prob))
+            .collect()
+    }
+
+    /// レベルごとの確率の差分
+    pub fn level_deltas(&self) -> Vec<ProbDelta> {
+        self.old_result
+            .level_result
+            .iter()
+            .zip(self.new_result.level_result.iter())
+            .map(|(old_prob, new_prob)| ProbDelta::new(*old_prob, *new_prob))
+            .collect()
+    }
+}
+
+/// 二つのデッキで枚数の異なるカードを取得する．メインデッキ，エクストラデッキの順に，
+/// それぞれ比較元のデッキの順番の後に比較先にのみ含まれるカードを並べる．
+pub fn card_diff(old_deck: &Deck, new_deck: &Deck) -> Vec<CardDiff> {
+    let main_diff = old_deck
+        .iter()
+        .chain(
+            new_deck
+                .iter()
+                .filter(|card| old_deck.index_of(&card.name).is_none()),
+        )
+        .map(|card| CardDiff {
+            name: card.name.to_string(),
+            extra: false,
+            old_number: old_deck.number_of(&card.name),
+            new_number: new_deck.number_of(&card.name),
+        });
+    let extra_diff = old_deck
+        .extra()
+        .iter()
+        .chain(new_deck.extra().iter().filter(|card| {
+            !old_deck
+                .extra()
+                .iter()
+                .any(|old_card| old_card.name == card.name)
+        }))
+        .map(|card| CardDiff {
+            name: card.name.to_string(),
+            extra: true,
+            old_number: old_deck.extra_number_of(&card.name),
+            new_number: new_deck.extra_number_of(&card.name),
+        });
+
+    main_diff
+        .chain(extra_diff)
+        .filter(|diff| diff.old_number != diff.new_number)
+        .collect()
+}
+
+/// 二つのデッキについて同じパターンで全探索し，比較する．`progress`はデッキごとに進捗を通知し直す．
+pub fn compare_decks(
+    old_deck: &Deck,
+    new_deck: &Deck,
+    patterns: &[InitialPattern],
+    draw_n: u64,
+    progress: &mut Progress,
+) -> Result<CompareResult, AppError> {
+    let table = BinomialTable::new(std::cmp::max(old_deck.size(), new_deck.size()) as u64);
+
+    Ok(CompareResult {
+        card_diff: card_diff(old_deck, new_deck),
+        old_result: all_search_pattern(old_deck, patterns, draw_n, &table, progress)?,
+        new_result: all_search_pattern(new_deck, patterns, draw_n, &table, progress)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{card_diff, compare_decks, CardDiff};
+    use crate::analysis::test_util::{deck, single_pattern};
+    use crate::progress::Progress;
+
+    #[test]
+    fn test_compare_decks() {
+        let old_deck = deck(&[("溟界の滓－ヌル", 1), ("灰流うらら", 3)]);
+        let new_deck = deck(&[("溟界の滓－ヌル", 2), ("灰流うらら", 1), ("増殖するG", 1)]);
+        let patterns = vec![single_pattern("溟界の滓－ヌル")];
+
+        let result =
+            compare_decks(&old_deck, &new_deck, &patterns, 1, &mut Progress::silent()).unwrap();
+        assert_eq!(
+            result.card_diff,
+            vec![
+                CardDiff {
+                    name: "溟界の滓－ヌル".to_string(),
+                    extra: false,
+                    old_number: 1,
+                    new_number: 2
+                },
+                CardDiff {
+                    name: "灰流うらら".to_string(),
+                    extra: false,
+                    old_number: 3,
+                    new_number: 1
+                },
+                CardDiff {
+                    name: "増殖するG".to_string(),
+                    extra: false,
+                    old_number: 0,
+                    new_number: 1
+                },
+            ]
+        );
+
+        let deltas = result.pattern_deltas();
+        assert_eq!(deltas[0].old_prob, 0.25);
+        assert_eq!(deltas[0].new_prob, 0.5);
+        assert_eq!(deltas[0].abs_delta, 0.25);
+        assert_eq!(deltas[0].rel_delta, Some(1.0));
+
+        // 比較元で確率が0の場合は相対差分が無い
+        let no_null_deck = deck(&[("灰流うらら", 4)]);
+        let result = compare_decks(
+            &no_null_deck,
+            &new_deck,
+            &patterns,
+            1,
+            &mut Progress::silent(),
+        )
+        .unwrap();
+        assert_eq!(result.pattern_deltas()[0].rel_delta, None);
+        // 比較先に無いカードは0枚として扱う
+        let diff = card_diff(&new_deck, &no_null_deck);
+        assert_eq!(
+            diff.iter()
+                .map(|diff| (diff.name.as_str(), diff.old_number, diff.new_number))
+                .collect::<Vec<_>>(),
+            vec![
+                ("溟界の滓－ヌル", 2, 0),
+                ("灰流うらら", 1, 4),
+                ("増殖するG", 1, 0)
+            ]
+        );
+        // 同じデッキの場合は枚数の差分が無い
+        let result =
+            compare_decks(&old_deck, &old_deck, &patterns, 1, &mut Progress::silent()).unwrap();
+        assert!(result.card_diff.is_empty());
+        assert_eq!(result.level_deltas()[1].abs_delta, 0.0);
+    }
+}
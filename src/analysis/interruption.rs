@@ -0,0 +1,132 @@
+use crate::all_search_v2::{search_hand_types, CardTypes};
+use crate::combinations::{validate_draw_n, BinomialTable};
+use crate::domain::{validate_patterns, Deck, InitialPattern, StringOrList};
+use crate::progress::Progress;
+use crate::AppError;
+
+/// 妨害カードの枚数の分析結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterruptionResult {
+    /// 手札の妨害カードの枚数ごとの確率．インデックスが枚数
+    pub number_result: Vec<f64>,
+    /// レベルごとに，そのレベル以上のパターンにマッチしかつ妨害カードがちょうどk枚である確率．
+    /// `level_number_result[level][k]`
+    pub level_number_result: Vec<Vec<f64>>,
+}
+
+impl InterruptionResult {
+    /// レベルごとに，そのレベル以上のパターンにマッチしかつ妨害カードが`min_n`枚以上ある確率
+    pub fn level_result_with(&self, min_n: usize) -> Vec<f64> {
+        self.level_number_result
+            .iter()
+            .map(|number_result| number_result.iter().skip(min_n).sum())
+            .collect()
+    }
+}
+
+/// 手札に含まれる妨害カード(`interruptions`)の枚数の分布を，レベルとの同時分布として全探索で求める．
+/// 手札の種類一つごとに`progress`を進める．
+pub fn interruption_search(
+    deck: &Deck,
+    patterns: &[InitialPattern],
+    interruptions: &StringOrList,
+    draw_n: u64,
+    progress: &mut Progress,
+) -> Result<InterruptionResult, AppError> {
+    let max_level = validate_patterns(patterns)?;
+
+    // 妨害カードである種類
+    let interruptions = deck.resolve(interruptions)?;
+    let card_types = CardTypes::new(deck);
+    // 手札の妨害カードの枚数ごとのカウンタを確保する前に検証する
+    validate_draw_n(card_types.card_n(), draw_n)?;
+    let is_interruption: Vec<bool> = card_types
+        .names
+        .iter()
+        .map(|name| interruptions.contains(name))
+        .collect();
+
+    let table = BinomialTable::new(card_types.card_n());
+
+    let mut numbers_per_number: Vec<u128> = vec![0; draw_n as usize + 1];
+    let mut numbers_per_level_number: Vec<Vec<u128>> =
+        vec![vec![0; draw_n as usize + 1]; max_level as usize + 1];
+
+    let all_pattern_number = search_hand_types(
+        deck,
+        patterns,
+        draw_n,
+        &table,
+        progress,
+        |hand_type, _, level_flag| {
+            let interruption_n = hand_type
+                .numbers
+                .iter()
+                .zip(is_interruption.iter())
+                .filter(|(_, is_interruption)| **is_interruption)
+                .fold(0_usize, |acc, (n, _)| acc + *n as usize);
+
+            numbers_per_number[interruption_n] += hand_type.weight;
+
+            if let Some(level) = level_flag {
+                for numbers_per_number in numbers_per_level_number.iter_mut().take(level + 1) {
+                    numbers_per_number[interruption_n] += hand_type.weight;
+                }
+            }
+        },
+    )?;
+
+    let to_prob = |numbers: Vec<u128>| -> Vec<f64> {
+        numbers
+            .into_iter()
+            .map(|n| n as f64 / all_pattern_number as f64)
+            .collect()
+    };
+
+    Ok(InterruptionResult {
+        number_result: to_prob(numbers_per_number),
+        level_number_result: numbers_per_level_number.into_iter().map(to_prob).collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::interruption_search;
+    use crate::analysis::test_util::{deck, single_pattern};
+    use crate::domain::StringOrList;
+    use crate::progress::Progress;
+    use crate::AppError;
+
+    #[test]
+    fn test_interruption_search() {
+        let deck = deck(&[("溟界の滓－ヌル", 1), ("灰流うらら", 1), ("増殖するG", 2)]);
+        let patterns = vec![single_pattern("溟界の滓－ヌル")];
+        let interruptions = StringOrList::String("灰流うらら".to_string());
+
+        let result =
+            interruption_search(&deck, &patterns, &interruptions, 2, &mut Progress::silent())
+                .unwrap();
+
+        // 6通りのうち，うららを含むのは3通り
+        assert_eq!(result.number_result, vec![0.5, 0.5, 0.0]);
+        // ヌルを含む3通りのうち，うららを含むのは1通り
+        assert_eq!(
+            result.level_number_result[1],
+            vec![2.0 / 6.0, 1.0 / 6.0, 0.0]
+        );
+        assert_eq!(result.level_result_with(1), vec![1.0 / 6.0, 1.0 / 6.0]);
+
+        // デッキの枚数を超えるドロー数．カウンタを確保する前にエラーとする
+        for draw_n in [5, u64::MAX] {
+            let err = interruption_search(
+                &deck,
+                &patterns,
+                &interruptions,
+                draw_n,
+                &mut Progress::silent(),
+            )
+            .unwrap_err();
+            assert!(matches!(err, AppError::InvalidDataError(_)));
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use super::compare::{card_diff, CardDiff, ProbDelta};
+use crate::all_search::AllSearchResult;
+use crate::all_search_v2::all_search_pattern;
+use crate::combinations::BinomialTable;
+use crate::domain::{Deck, InitialPattern, SidingPlan, MAIN_DECK_MAX};
+use crate::progress::Progress;
+use crate::AppError;
+
+/// サイドチェンジの計画一つの結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchupRow {
+    /// 計画の名前(対戦相手)
+    pub plan: String,
+    /// サイドチェンジで枚数の変わったカード
+    pub card_diff: Vec<CardDiff>,
+    pub result: AllSearchResult,
+}
+
+/// マッチ1戦目のデッキと，サイドチェンジの計画ごとのデッキの全探索の結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchupTable {
+    pub game1: AllSearchResult,
+    pub rows: Vec<MatchupRow>,
+}
+
+impl MatchupTable {
+    /// 1戦目からのパターンごとの確率の差分
+    pub fn pattern_deltas(&self, row: &MatchupRow) -> Vec<ProbDelta> {
+        deltas(&self.game1.pattern_result, &row.result.pattern_result)
+    }
+
+    /// 1戦目からのレベルごとの確率の差分
+    pub fn level_deltas(&self, row: &MatchupRow) -> Vec<ProbDelta> {
+        deltas(&self.game1.level_result, &row.result.level_result)
+    }
+}
+
+fn deltas(old_probs: &[f64], new_probs: &[f64]) -> Vec<ProbDelta> {
+    old_probs
+        .iter()
+        .zip(new_probs.iter())
+        .map(|(old_prob, new_prob)| ProbDelta::new(*old_prob, *new_prob))
+        .collect()
+}
+
+/// 1戦目のデッキと，`plans`それぞれでサイドチェンジしたデッキを同じパターンで全探索する．
+/// 1戦目のデッキとサイドチェンジ後のデッキは全て大会で使用できる枚数である必要がある．
+/// `progress`はデッキごとに全探索の進捗を通知し直す．
+pub fn matchup_search(
+    deck: &Deck,
+    plans: &[SidingPlan],
+    patterns: &[InitialPattern],
+    draw_n: u64,
+    progress: &mut Progress,
+) -> Result<MatchupTable, AppError> {
+    deck.validate_size()?;
+    let table = BinomialTable::new(MAIN_DECK_MAX as u64);
+
+    let game1 = all_search_pattern(deck, patterns, draw_n, &table, progress)?;
+
+    let mut rows = Vec::new();
+    for plan in plans.iter() {
+        let sided = plan.apply(deck)?;
+        rows.push(MatchupRow {
+            plan: plan.name.to_string(),
+            card_diff: card_diff(deck, &sided),
+            result: all_search_pattern(&sided, patterns, draw_n, &table, progress)?,
+        });
+    }
+
+    Ok(MatchupTable { game1, rows })
+}
+
+#[cfg(test)]
+mod test {
+    use super::matchup_search;
+    use crate::analysis::CardDiff;
+    use crate::domain::{Card, Deck, InitialPattern, SideCard, SidingPlan, StringOrList};
+    use crate::progress::Progress;
+
+    #[test]
+    fn test_matchup_search() {
+        let deck = Deck::new(vec![
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "増殖するG".to_string(),
+                number: 37,
+                ..Default::default()
+            },
+        ])
+        .unwrap()
+        .with_side(vec![Card {
+            name: "溟界の滓－ナイア".to_string(),
+            number: 3,
+            ..Default::default()
+        }])
+        .unwrap();
+
+        let patterns = vec![InitialPattern {
+            name: "溟界".to_string(),
+            first: StringOrList::List(vec![
+                "溟界の滓－ヌル".to_string(),
+                "溟界の滓－ナイア".to_string(),
+            ]),
+            second: None,
+            third: None,
+            level: 1,
+            extra: None,
+        }];
+        let plans = vec![SidingPlan {
+            name: "vs ティアラメンツ".to_string(),
+            cards_in: vec![SideCard {
+                name: "溟界の滓－ナイア".to_string(),
+                number: 3,
+                extra: false,
+            }],
+            cards_out: vec![SideCard {
+                name: "増殖するG".to_string(),
+                number: 3,
+                extra: false,
+            }],
+        }];
+
+        let table = matchup_search(&deck, &plans, &patterns, 5, &mut Progress::silent()).unwrap();
+        assert_eq!(table.rows.len(), 1);
+        assert_eq!(table.rows[0].card_diff.len(), 2);
+
+        let deltas = table.level_deltas(&table.rows[0]);
+        assert!(deltas[1].abs_delta > 0.0);
+        assert_eq!(deltas[1].old_prob, table.game1.level_result[1]);
+
+        // エクストラデッキのみを入れ替える場合も差分に含める
+        let extra_deck = deck
+            .clone()
+            .with_extra(vec![Card {
+                name: "アクセスコード・トーカー".to_string(),
+                number: 1,
+                ..Default::default()
+            }])
+            .unwrap()
+            .with_side(vec![Card {
+                name: "I:Pマスカレーナ".to_string(),
+                number: 1,
+                ..Default::default()
+            }])
+            .unwrap();
+        let extra_plans = vec![SidingPlan {
+            name: "vs 烙印".to_string(),
+            cards_in: vec![SideCard {
+                name: "I:Pマスカレーナ".to_string(),
+                number: 1,
+                extra: true,
+            }],
+            cards_out: vec![SideCard {
+                name: "アクセスコード・トーカー".to_string(),
+                number: 1,
+                extra: true,
+            }],
+        }];
+        let extra_table = matchup_search(
+            &extra_deck,
+            &extra_plans,
+            &patterns,
+            5,
+            &mut Progress::silent(),
+        )
+        .unwrap();
+        assert_eq!(
+            extra_table.rows[0].card_diff,
+            vec![
+                CardDiff {
+                    name: "アクセスコード・トーカー".to_string(),
+                    extra: true,
+                    old_number: 1,
+                    new_number: 0,
+                },
+                CardDiff {
+                    name: "I:Pマスカレーナ".to_string(),
+                    extra: true,
+                    old_number: 0,
+                    new_number: 1,
+                },
+            ]
+        );
+        assert_eq!(extra_table.rows[0].result, extra_table.game1);
+
+        // 1戦目のデッキが40枚未満の場合
+        let small_deck = deck.with_cards(deck[..1].to_vec()).unwrap();
+        assert!(
+            matchup_search(&small_deck, &plans, &patterns, 5, &mut Progress::silent()).is_err()
+        );
+    }
+}
@@ -0,0 +1,533 @@
+use crate::all_search_v2::all_search_pattern;
+use crate::combinations::BinomialTable;
+use crate::domain::{validate_patterns, Card, Deck, InitialPattern};
+use crate::progress::Progress;
+use crate::AppError;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// 枚数を調整する候補のカード．`card.number`が最初の枚数
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Candidate {
+    #[serde(flatten)]
+    pub card: Card,
+    pub min: u8,
+    pub max: u8,
+}
+
+/// 最適化の目的関数
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Objective {
+    /// `level`以上のパターンにマッチする確率を最大化する
+    Level { level: u8 },
+    /// レベルごとの確率の重み付き和を最大化する．`weights[level]`がそのレベルの重み
+    Weighted { weights: Vec<f64> },
+}
+
+impl Objective {
+    /// パターンの最大のレベルに対して有効かどうかを検証する．範囲外のレベルやレベルの数より多い重みはエラー
+    pub fn validate(&self, max_level: u8) -> Result<(), AppError> {
+        match self {
+            Self::Level { level } if *level > max_level => Err(AppError::InvalidDataError(
+                format!("Objective level {level} is larger than max level {max_level}."),
+            )),
+            Self::Weighted { weights } if weights.len() > max_level as usize + 1 => {
+                Err(AppError::InvalidDataError(format!(
+                    "Objective has {} weights for {} levels.",
+                    weights.len(),
+                    max_level as usize + 1
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// レベルごとの確率からスコアを計算する．`validate`で検証したレベルの範囲を前提とする．
+    pub fn score(&self, level_result: &[f64]) -> f64 {
+        match self {
+            Self::Level { level } => level_result.get(*level as usize).copied().unwrap_or(0.0),
+            Self::Weighted { weights } => weights
+                .iter()
+                .zip(level_result.iter())
+                .map(|(weight, prob)| weight * prob)
+                .sum(),
+        }
+    }
+}
+
+/// 焼きなまし法の設定
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub struct Annealing {
+    /// 反復回数
+    pub iteration_n: usize,
+    /// 初期温度
+    pub initial_temperature: f64,
+    /// 反復ごとに温度に掛ける冷却率
+    pub cooling_rate: f64,
+    /// 乱数のシード
+    pub seed: Option<u64>,
+}
+
+impl Annealing {
+    /// 初期温度が有限の正の値で，冷却率が0より大きく1未満かどうかを検証する．
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(self.initial_temperature.is_finite() && self.initial_temperature > 0.0) {
+            return Err(AppError::InvalidDataError(format!(
+                "initial_temperature must be positive and finite: {}",
+                self.initial_temperature
+            )));
+        }
+        if !(0.0 < self.cooling_rate && self.cooling_rate < 1.0) {
+            return Err(AppError::InvalidDataError(format!(
+                "cooling_rate must be in (0, 1): {}",
+                self.cooling_rate
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 最適化の設定
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub struct OptimizerSetting {
+    /// デッキの枚数．候補の枚数の合計は`deck_size`から固定のカードの枚数を引いた値で，最適化の間は変わらない．
+    pub deck_size: usize,
+    pub draw_n: u64,
+    pub objective: Objective,
+    /// 出力するデッキの数
+    pub top_k: usize,
+    /// 山登り法の後に行う焼きなまし法の設定．Noneの場合は山登り法のみ
+    pub annealing: Option<Annealing>,
+}
+
+/// 最適化で得られたデッキ
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimizedDeck {
+    /// 最適化したデッキ．エクストラデッキとサイドデッキは元のデッキのまま
+    pub deck: Deck,
+    pub score: f64,
+    /// 最初のデッキからの候補のカードの枚数の差分．差分のあるカードのみ
+    pub diff: Vec<(String, i16)>,
+}
+
+/// 候補のカードの枚数を評価する．評価済みの枚数はキャッシュする．
+struct Evaluator<'a, 'p> {
+    /// メインデッキが固定のカードであるデッキ
+    core: &'a Deck,
+    candidates: &'a [Candidate],
+    patterns: &'a [InitialPattern],
+    setting: &'a OptimizerSetting,
+    progress: &'a mut Progress<'p>,
+    /// デッキの枚数までのnCkの表
+    table: BinomialTable,
+    cache: HashMap<Vec<u8>, f64>,
+}
+
+impl Evaluator<'_, '_> {
+    /// 候補のカードの枚数からデッキを作成する．エクストラデッキとサイドデッキは`core`のものを用いる．
+    fn deck(&self, numbers: &[u8]) -> Result<Deck, AppError> {
+        let mut cards = self.core.iter().cloned().collect::<Vec<_>>();
+        for (candidate, n) in self.candidates.iter().zip(numbers.iter()) {
+            if *n > 0 {
+                cards.push(Card {
+                    number: *n,
+                    ..candidate.card.clone()
+                });
+            }
+        }
+        self.core.with_cards(cards)
+    }
+
+    /// 候補の枚数の合計が`target`になるように最初のデッキの枚数を調整する．
+    /// 足りない場合は候補の順に`max`まで増やし，多い場合は候補の順に`min`まで減らす．
+    fn fit(&self, numbers: &[u8], target: usize) -> Vec<u8> {
+        let mut numbers = numbers.to_vec();
+        let mut total = numbers.iter().map(|n| *n as usize).sum::<usize>();
+
+        for (n, candidate) in numbers.iter_mut().zip(self.candidates.iter()) {
+            if total < target {
+                let add = ((candidate.max - *n) as usize).min(target - total);
+                *n += add as u8;
+                total += add;
+            } else if total > target {
+                let remove = ((*n - candidate.min) as usize).min(total - target);
+                *n -= remove as u8;
+                total -= remove;
+            }
+        }
+        numbers
+    }
+
+    fn score(&mut self, numbers: &[u8]) -> Result<f64, AppError> {
+        if let Some(score) = self.cache.get(numbers) {
+            return Ok(*score);
+        }
+
+        let result = all_search_pattern(
+            &self.deck(numbers)?,
+            self.patterns,
+            self.setting.draw_n,
+            &self.table,
+            self.progress,
+        )?;
+        let score = self.setting.objective.score(&result.level_result);
+        self.cache.insert(numbers.to_vec(), score);
+
+        Ok(score)
+    }
+
+    /// 候補`add_i`を一枚増やし，候補`remove_i`を一枚減らした枚数．範囲外の場合はNone
+    fn swap(&self, numbers: &[u8], add_i: usize, remove_i: usize) -> Option<Vec<u8>> {
+        if add_i == remove_i
+            || numbers[add_i] >= self.candidates[add_i].max
+            || numbers[remove_i] <= self.candidates[remove_i].min
+        {
+            return None;
+        }
+
+        let mut next_numbers = numbers.to_vec();
+        next_numbers[add_i] += 1;
+        next_numbers[remove_i] -= 1;
+        Some(next_numbers)
+    }
+}
+
+/// `core`のメインデッキを固定のカードとし，候補のカードの枚数を変えてデッキを最適化する．
+/// エクストラデッキとサイドデッキは`core`のものをそのまま用いる．デッキの枚数を保つように候補の枚数を一枚ずつ入れ替え，
+/// 山登り法で局所最適解を求めた後，設定されている場合は焼きなまし法で探索を続ける．
+/// 評価したデッキのうちスコアの高い順に`top_k`個を返す．候補が無い場合や，目的関数のレベルがパターンに無い場合，
+/// 候補の`min`と`max`の範囲で`deck_size`にできない場合はエラー
+/// `progress`はデッキを評価するごとに進捗を通知し直す．
+pub fn optimize_deck(
+    core: &Deck,
+    candidates: &[Candidate],
+    patterns: &[InitialPattern],
+    setting: &OptimizerSetting,
+    progress: &mut Progress,
+) -> Result<Vec<OptimizedDeck>, AppError> {
+    let max_level = validate_patterns(patterns)?;
+    setting.objective.validate(max_level)?;
+    if let Some(annealing) = setting.annealing.as_ref() {
+        annealing.validate()?;
+    }
+
+    if candidates.is_empty() {
+        return Err(AppError::InvalidDataError(
+            "No candidates to optimize.".to_string(),
+        ));
+    }
+    for Candidate { card, min, max } in candidates.iter() {
+        if !(min <= &card.number && &card.number <= max) {
+            return Err(AppError::InvalidDataError(format!(
+                "Invalid candidate: {}",
+                card.name
+            )));
+        }
+    }
+
+    // 候補の枚数の合計．候補の枚数を一枚ずつ入れ替えるため，最適化の間は変わらない
+    let candidate_n = setting.deck_size.checked_sub(core.size());
+    let min_n = candidates
+        .iter()
+        .map(|candidate| candidate.min as usize)
+        .sum::<usize>();
+    let max_n = candidates
+        .iter()
+        .map(|candidate| candidate.max as usize)
+        .sum::<usize>();
+    let candidate_n = match candidate_n {
+        Some(n) if min_n <= n && n <= max_n => n,
+        _ => {
+            return Err(AppError::InvalidDataError(format!(
+                "Deck size {} cannot be reached: core {} + candidates {min_n}-{max_n}.",
+                setting.deck_size,
+                core.size()
+            )));
+        }
+    };
+
+    let initial_numbers: Vec<u8> = candidates
+        .iter()
+        .map(|candidate| candidate.card.number)
+        .collect();
+
+    let mut evaluator = Evaluator {
+        core,
+        candidates,
+        patterns,
+        setting,
+        progress,
+        table: BinomialTable::new(setting.deck_size as u64),
+        cache: HashMap::new(),
+    };
+
+    // 山登り法．最初のデッキの枚数が`deck_size`と異なる場合は調整したデッキから始める
+    let mut numbers = evaluator.fit(&initial_numbers, candidate_n);
+    let mut score = evaluator.score(&numbers)?;
+    loop {
+        let mut best: Option<(Vec<u8>, f64)> = None;
+        for add_i in 0..candidates.len() {
+            for remove_i in 0..candidates.len() {
+                if let Some(next_numbers) = evaluator.swap(&numbers, add_i, remove_i) {
+                    let next_score = evaluator.score(&next_numbers)?;
+                    if next_score > best.as_ref().map_or(score, |(_, s)| *s) {
+                        best = Some((next_numbers, next_score));
+                    }
+                }
+            }
+        }
+
+        match best {
+            Some((next_numbers, next_score)) => {
+                numbers = next_numbers;
+                score = next_score;
+            }
+            None => break, // 局所最適解
+        }
+    }
+
+    // 焼きなまし法．入れ替える候補が二つ以上無い場合は近傍が無いため行わない
+    if let Some(annealing) = setting.annealing.as_ref().filter(|_| candidates.len() >= 2) {
+        let mut rng = match annealing.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut temperature = annealing.initial_temperature;
+
+        for _ in 0..annealing.iteration_n {
+            let add_i = rng.gen_range(0..candidates.len());
+            let remove_i = rng.gen_range(0..candidates.len());
+
+            if let Some(next_numbers) = evaluator.swap(&numbers, add_i, remove_i) {
+                let next_score = evaluator.score(&next_numbers)?;
+                let delta = next_score - score;
+                if delta >= 0.0 || rng.gen::<f64>() < (delta / temperature).exp() {
+                    numbers = next_numbers;
+                    score = next_score;
+                }
+            }
+
+            temperature *= annealing.cooling_rate;
+        }
+    }
+
+    let mut scores: Vec<(Vec<u8>, f64)> = evaluator
+        .cache
+        .iter()
+        .map(|(numbers, score)| (numbers.clone(), *score))
+        .collect();
+    scores.sort_by(|(_, x), (_, y)| y.total_cmp(x));
+
+    scores
+        .into_iter()
+        .take(setting.top_k)
+        .map(|(numbers, score)| {
+            Ok(OptimizedDeck {
+                deck: evaluator.deck(&numbers)?,
+                score,
+                diff: candidates
+                    .iter()
+                    .zip(numbers.iter().zip(initial_numbers.iter()))
+                    .filter(|(_, (n, initial_n))| n != initial_n)
+                    .map(|(candidate, (n, initial_n))| {
+                        (
+                            candidate.card.name.to_string(),
+                            *n as i16 - *initial_n as i16,
+                        )
+                    })
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{optimize_deck, Annealing, Candidate, Objective, OptimizerSetting};
+    use crate::domain::{Card, Deck, InitialPattern, StringOrList};
+    use crate::progress::Progress;
+
+    #[test]
+    fn test_optimize_deck() {
+        let core = Deck::new(vec![Card {
+            name: "灰流うらら".to_string(),
+            number: 3,
+            ..Default::default()
+        }])
+        .unwrap()
+        .with_extra(vec![Card {
+            name: "神聖魔皇后セレーネ".to_string(),
+            number: 1,
+            ..Default::default()
+        }])
+        .unwrap();
+        let candidates = vec![
+            Candidate {
+                card: Card {
+                    name: "溟界の滓－ヌル".to_string(),
+                    number: 0,
+                    ..Default::default()
+                },
+                min: 0,
+                max: 3,
+            },
+            Candidate {
+                card: Card {
+                    name: "増殖するG".to_string(),
+                    number: 3,
+                    ..Default::default()
+                },
+                min: 0,
+                max: 3,
+            },
+        ];
+        // エクストラデッキのカードを必要とするパターンも，エクストラデッキを残すためマッチする
+        let patterns = vec![InitialPattern {
+            name: "ヌル".to_string(),
+            first: StringOrList::String("溟界の滓－ヌル".to_string()),
+            second: None,
+            third: None,
+            level: 1,
+            extra: Some(StringOrList::String("神聖魔皇后セレーネ".to_string())),
+        }];
+
+        let mut setting = OptimizerSetting {
+            deck_size: 6,
+            draw_n: 2,
+            objective: Objective::Level { level: 1 },
+            top_k: 2,
+            annealing: None,
+        };
+
+        let decks = optimize_deck(
+            &core,
+            &candidates,
+            &patterns,
+            &setting,
+            &mut Progress::silent(),
+        )
+        .unwrap();
+        assert_eq!(decks.len(), 2);
+        assert_eq!(
+            decks[0].diff,
+            vec![
+                ("溟界の滓－ヌル".to_string(), 3),
+                ("増殖するG".to_string(), -3)
+            ]
+        );
+        assert!((decks[0].score - 0.8).abs() < 1e-12); // 1 - 3C2 / 6C2
+        assert!(decks[0].score > decks[1].score);
+        assert_eq!(decks[0].deck.number_of("溟界の滓－ヌル"), 3);
+        assert_eq!(decks[0].deck.extra(), core.extra());
+
+        setting.annealing = Some(Annealing {
+            iteration_n: 20,
+            initial_temperature: 0.1,
+            cooling_rate: 0.9,
+            seed: Some(0),
+        });
+        let decks = optimize_deck(
+            &core,
+            &candidates,
+            &patterns,
+            &setting,
+            &mut Progress::silent(),
+        )
+        .unwrap();
+        assert!((decks[0].score - 0.8).abs() < 1e-12);
+
+        // 候補が無い場合
+        assert!(optimize_deck(&core, &[], &patterns, &setting, &mut Progress::silent()).is_err());
+        // 候補が一つの場合は入れ替えられないため最初のデッキのみ
+        let decks = optimize_deck(
+            &core,
+            &candidates[..1],
+            &patterns,
+            &OptimizerSetting {
+                deck_size: 3,
+                ..setting.clone()
+            },
+            &mut Progress::silent(),
+        )
+        .unwrap();
+        assert_eq!(decks.len(), 1);
+        assert!(decks[0].diff.is_empty());
+
+        // 最初のデッキより多い枚数．候補の枚数を増やしてから探索する
+        let decks = optimize_deck(
+            &core,
+            &candidates,
+            &patterns,
+            &OptimizerSetting {
+                deck_size: 8,
+                ..setting.clone()
+            },
+            &mut Progress::silent(),
+        )
+        .unwrap();
+        assert!(decks.iter().all(|deck| deck.deck.size() == 8));
+        assert_eq!(decks[0].deck.number_of("溟界の滓－ヌル"), 3);
+        // 候補の`min`と`max`で届かない枚数や，固定のカードより少ない枚数
+        for deck_size in [10, 2] {
+            let setting = OptimizerSetting {
+                deck_size,
+                ..setting.clone()
+            };
+            assert!(optimize_deck(
+                &core,
+                &candidates,
+                &patterns,
+                &setting,
+                &mut Progress::silent()
+            )
+            .is_err());
+        }
+
+        // 焼きなまし法の不正な設定
+        for (initial_temperature, cooling_rate) in [(0.0, 0.9), (0.1, 1.0), (0.1, 0.0)] {
+            let setting = OptimizerSetting {
+                annealing: Some(Annealing {
+                    iteration_n: 20,
+                    initial_temperature,
+                    cooling_rate,
+                    seed: Some(0),
+                }),
+                ..setting.clone()
+            };
+            assert!(optimize_deck(
+                &core,
+                &candidates,
+                &patterns,
+                &setting,
+                &mut Progress::silent()
+            )
+            .is_err());
+        }
+
+        // パターンに無いレベル
+        setting.objective = Objective::Level { level: 2 };
+        assert!(optimize_deck(
+            &core,
+            &candidates,
+            &patterns,
+            &setting,
+            &mut Progress::silent()
+        )
+        .is_err());
+        setting.objective = Objective::Weighted {
+            weights: vec![0.0, 1.0, 2.0],
+        };
+        assert!(optimize_deck(
+            &core,
+            &candidates,
+            &patterns,
+            &setting,
+            &mut Progress::silent()
+        )
+        .is_err());
+    }
+}
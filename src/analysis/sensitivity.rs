@@ -0,0 +1,176 @@
+use crate::all_search::AllSearchResult;
+use crate::all_search_v2::all_search_pattern;
+use crate::combinations::BinomialTable;
+use crate::domain::{Card, Deck, InitialPattern};
+use crate::progress::Progress;
+use crate::AppError;
+
+/// デッキの枚数を保つために用いる，どのパターンにも含まれない汎用カードの名前
+pub const BLANK_CARD_NAME: &str = "(汎用カード)";
+
+/// 一枚のカードの枚数を変えた場合の確率の変化
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardSensitivity {
+    pub name: String,
+    /// 汎用カード一枚をこのカードに置き換えた場合の確率の変化
+    pub plus: AllSearchResult,
+    /// このカード一枚を汎用カードに置き換えた場合の確率の変化．デッキに0枚のカードは減らせないためNone
+    pub minus: Option<AllSearchResult>,
+}
+
+/// 枚数の感度分析の結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensitivityResult {
+    /// 元のデッキの確率
+    pub base: AllSearchResult,
+    /// カードごとの確率の変化．デッキと同じ順番
+    pub cards: Vec<CardSensitivity>,
+}
+
+impl SensitivityResult {
+    /// 一枚増やした場合の`level`の確率の変化を，大きい順に並べる．
+    pub fn sorted_by_plus(&self, level: usize) -> Vec<(&str, f64)> {
+        sorted_by_delta(self.cards.iter().map(|card| {
+            (
+                card.name.as_str(),
+                card.plus.level_result.get(level).copied().unwrap_or(0.0),
+            )
+        }))
+    }
+
+    /// 一枚減らした場合の`level`の確率の変化を，小さい(確率が大きく下がる)順に並べる．減らせないカードは含まない．
+    pub fn sorted_by_minus(&self, level: usize) -> Vec<(&str, f64)> {
+        let mut sorted = sorted_by_delta(self.cards.iter().filter_map(|card| {
+            let minus = card.minus.as_ref()?;
+            Some((
+                card.name.as_str(),
+                minus.level_result.get(level).copied().unwrap_or(0.0),
+            ))
+        }));
+        sorted.reverse();
+        sorted
+    }
+}
+
+/// 変化の大きい順に並べる．
+fn sorted_by_delta<'a>(deltas: impl Iterator<Item = (&'a str, f64)>) -> Vec<(&'a str, f64)> {
+    let mut deltas = deltas.collect::<Vec<_>>();
+    deltas.sort_by(|(_, x), (_, y)| y.total_cmp(x));
+    deltas
+}
+
+/// 二つの結果の差分(`result` - `base`)
+fn delta(result: &AllSearchResult, base: &AllSearchResult) -> AllSearchResult {
+    let sub =
+        |x: &[f64], y: &[f64]| -> Vec<f64> { x.iter().zip(y.iter()).map(|(x, y)| x - y).collect() };
+
+    AllSearchResult {
+        pattern_result: sub(&result.pattern_result, &base.pattern_result),
+        level_result: sub(&result.level_result, &base.level_result),
+    }
+}
+
+/// `deck`に汎用カードを`blank_n`枚加え，`card_i`番目のカードの枚数を`diff`だけ変える．枚数が範囲外になる場合はエラー
+fn modified_deck(
+    deck: &Deck,
+    card_i: Option<usize>,
+    diff: i8,
+    blank_n: u8,
+) -> Result<Deck, AppError> {
+    let mut cards = deck.to_vec();
+    if let Some(card_i) = card_i {
+        let card = &mut cards[card_i];
+        card.number = card.number.checked_add_signed(diff).ok_or_else(|| {
+            AppError::InvalidDataError(format!("Cannot change number of {}.", card.name))
+        })?;
+    }
+    if blank_n > 0 {
+        cards.push(Card {
+            name: BLANK_CARD_NAME.to_string(),
+            number: blank_n,
+            ..Default::default()
+        });
+    }
+    deck.with_cards(cards)
+}
+
+/// デッキのカードそれぞれについて，一枚増やした場合と減らした場合の全てのパターンとレベルの確率の変化を全探索で求める．
+/// デッキの枚数を保つため，増やす場合は汎用カード一枚を置き換え，減らす場合は汎用カード一枚に置き換える．
+/// デッキに汎用カードと同じ名前のカードがある場合はエラー．`progress`は全探索ごとに進捗を通知し直す．
+pub fn sensitivity_search(
+    deck: &Deck,
+    patterns: &[InitialPattern],
+    draw_n: u64,
+    progress: &mut Progress,
+) -> Result<SensitivityResult, AppError> {
+    if deck.index_of(BLANK_CARD_NAME).is_some() {
+        return Err(AppError::InvalidDataError(format!(
+            "Deck must not contain {BLANK_CARD_NAME}."
+        )));
+    }
+
+    // 一枚増やしたデッキまでの表
+    let table = BinomialTable::new(deck.size() as u64 + 1);
+    let mut search = |deck: &Deck| all_search_pattern(deck, patterns, draw_n, &table, progress);
+
+    let base = search(deck)?;
+    // 汎用カードを一枚加えたデッキ．一枚増やす場合の基準
+    let plus_base = search(&modified_deck(deck, None, 0, 1)?)?;
+
+    let mut cards = Vec::new();
+    for (card_i, card) in deck.iter().enumerate() {
+        let plus = search(&modified_deck(deck, Some(card_i), 1, 0)?)?;
+        let minus = match card.number {
+            0 => None,
+            _ => Some(search(&modified_deck(deck, Some(card_i), -1, 1)?)?),
+        };
+
+        cards.push(CardSensitivity {
+            name: card.name.to_string(),
+            plus: delta(&plus, &plus_base),
+            minus: minus.map(|minus| delta(&minus, &base)),
+        });
+    }
+
+    Ok(SensitivityResult { base, cards })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sensitivity_search, BLANK_CARD_NAME};
+    use crate::analysis::test_util::{deck, single_pattern};
+    use crate::progress::Progress;
+
+    #[test]
+    fn test_sensitivity_search() {
+        // 増殖するGはデッキに0枚
+        let deck = deck(&[("溟界の滓－ヌル", 1), ("灰流うらら", 3), ("増殖するG", 0)]);
+        let patterns = vec![single_pattern("溟界の滓－ヌル")];
+
+        let result = sensitivity_search(&deck, &patterns, 1, &mut Progress::silent()).unwrap();
+        assert_eq!(result.base.level_result, vec![0.25, 0.25]);
+
+        // ヌル: 5枚中2枚と汎用カードを加えた5枚中1枚の差，4枚中0枚と4枚中1枚の差
+        let minus = result.cards[0].minus.as_ref().unwrap();
+        assert!((result.cards[0].plus.level_result[1] - 0.2).abs() < 1e-12);
+        assert!((minus.level_result[1] + 0.25).abs() < 1e-12);
+        // うららはパターンに含まれないため変化しない
+        assert_eq!(result.cards[1].plus.level_result[1], 0.0);
+        assert_eq!(result.cards[1].minus.as_ref().unwrap().level_result[1], 0.0);
+        // 0枚のカードは増やせるが減らせない
+        assert_eq!(result.cards[2].plus.level_result[1], 0.0);
+        assert_eq!(result.cards[2].minus, None);
+
+        assert_eq!(result.sorted_by_plus(1)[0].0, "溟界の滓－ヌル");
+        assert_eq!(result.sorted_by_minus(1)[0].0, "溟界の滓－ヌル");
+        assert_eq!(result.sorted_by_minus(1).len(), 2);
+    }
+
+    #[test]
+    fn test_sensitivity_search_blank_name() {
+        // 汎用カードと同じ名前のカードがある場合
+        let deck = deck(&[("溟界の滓－ヌル", 1), (BLANK_CARD_NAME, 3)]);
+        let patterns = vec![single_pattern("溟界の滓－ヌル")];
+        assert!(sensitivity_search(&deck, &patterns, 1, &mut Progress::silent()).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use super::BLANK_CARD_NAME;
+use crate::all_search::AllSearchResult;
+use crate::all_search_v2::all_search_pattern;
+use crate::combinations::BinomialTable;
+use crate::domain::{Card, Deck, InitialPattern};
+use crate::progress::Progress;
+use crate::AppError;
+
+use serde::Deserialize;
+use std::ops::RangeInclusive;
+
+/// デッキの枚数を増やす際に加えるカード
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Filler {
+    /// どのパターンにも含まれない汎用カード
+    Blank,
+    /// 指定したカードを順にそれぞれ`number`枚まで加え，足りない場合は汎用カードを加える
+    Cards { cards: Vec<Card> },
+}
+
+impl Filler {
+    /// `deck`に`filler_n`枚加えたデッキ．汎用カードが255枚を超える場合や，デッキに汎用カードと同じ名前のカードがある場合はエラー
+    fn fill(&self, deck: &Deck, filler_n: usize) -> Result<Deck, AppError> {
+        let mut deck_cards = deck.to_vec();
+        let mut rest_n = filler_n;
+
+        if let Self::Cards { cards } = self {
+            for card in cards.iter() {
+                let n = std::cmp::min(card.number, u8::try_from(rest_n).unwrap_or(u8::MAX));
+                if n > 0 {
+                    deck_cards.push(Card {
+                        number: n,
+                        ..card.clone()
+                    });
+                    rest_n -= n as usize;
+                }
+            }
+        }
+
+        if rest_n > 0 {
+            if deck.index_of(BLANK_CARD_NAME).is_some() {
+                return Err(AppError::InvalidDataError(format!(
+                    "Deck must not contain {BLANK_CARD_NAME}."
+                )));
+            }
+            let number = u8::try_from(rest_n).map_err(|_| {
+                AppError::InvalidDataError(format!("Too many filler cards: {rest_n}"))
+            })?;
+            deck_cards.push(Card {
+                name: BLANK_CARD_NAME.to_string(),
+                number,
+                ..Default::default()
+            });
+        }
+
+        deck.with_cards(deck_cards)
+    }
+}
+
+/// デッキの枚数とドロー数の一つの組み合わせの結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepPoint {
+    pub deck_size: usize,
+    pub draw_n: u64,
+    pub result: AllSearchResult,
+}
+
+/// デッキを`filler`で`deck_sizes`の各枚数まで増やし，`draw_ns`の各ドロー数について全探索する．
+/// デッキの枚数，ドロー数の順に並べて返す．`progress`は全探索ごとに進捗を通知し直す．
+pub fn sweep_search(
+    deck: &Deck,
+    patterns: &[InitialPattern],
+    filler: &Filler,
+    deck_sizes: RangeInclusive<usize>,
+    draw_ns: RangeInclusive<u64>,
+    progress: &mut Progress,
+) -> Result<Vec<SweepPoint>, AppError> {
+    let deck_card_n = deck.size();
+
+    let table = BinomialTable::new(*deck_sizes.end() as u64);
+
+    let mut points = Vec::new();
+    for deck_size in deck_sizes {
+        let filler_n = deck_size.checked_sub(deck_card_n).ok_or_else(|| {
+            AppError::InvalidDataError(format!(
+                "Deck size {deck_size} is smaller than deck ({deck_card_n})."
+            ))
+        })?;
+        let filled_deck = filler.fill(deck, filler_n)?;
+
+        for draw_n in draw_ns.clone() {
+            points.push(SweepPoint {
+                deck_size,
+                draw_n,
+                result: all_search_pattern(&filled_deck, patterns, draw_n, &table, progress)?,
+            });
+        }
+    }
+
+    Ok(points)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sweep_search, Filler};
+    use crate::analysis::test_util::{deck, single_pattern};
+    use crate::domain::Card;
+    use crate::progress::Progress;
+
+    #[test]
+    fn test_sweep_search() {
+        let deck = deck(&[("溟界の滓－ヌル", 1)]);
+        let patterns = vec![single_pattern("溟界の滓－ヌル")];
+
+        let points = sweep_search(
+            &deck,
+            &patterns,
+            &Filler::Blank,
+            4..=5,
+            1..=2,
+            &mut Progress::silent(),
+        )
+        .unwrap();
+        let table = points
+            .iter()
+            .map(|point| (point.deck_size, point.draw_n, point.result.level_result[1]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            table,
+            vec![(4, 1, 0.25), (4, 2, 0.5), (5, 1, 0.2), (5, 2, 0.4)]
+        );
+
+        // フィラーにヌルを含める場合
+        let filler = Filler::Cards {
+            cards: vec![Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 1,
+                ..Default::default()
+            }],
+        };
+        let points = sweep_search(
+            &deck,
+            &patterns,
+            &filler,
+            4..=4,
+            1..=1,
+            &mut Progress::silent(),
+        )
+        .unwrap();
+        assert_eq!(points[0].result.level_result[1], 0.5);
+
+        assert!(sweep_search(
+            &deck,
+            &patterns,
+            &Filler::Blank,
+            0..=0,
+            1..=1,
+            &mut Progress::silent()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_sweep_search_large_filler() {
+        let deck = deck(&[("溟界の滓－ヌル", 1)]);
+        let patterns = vec![single_pattern("溟界の滓－ヌル")];
+
+        // 汎用カード255枚までは加えられる
+        let points = sweep_search(
+            &deck,
+            &patterns,
+            &Filler::Blank,
+            256..=256,
+            1..=1,
+            &mut Progress::silent(),
+        )
+        .unwrap();
+        assert!((points[0].result.level_result[1] - 1.0 / 256.0).abs() < 1e-12);
+        // 汎用カードが255枚を超える場合は切り捨てずにエラー
+        assert!(sweep_search(
+            &deck,
+            &patterns,
+            &Filler::Blank,
+            257..=257,
+            1..=1,
+            &mut Progress::silent()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use deck_analyzer::all_search_v2;
+use deck_analyzer::combinations::{combination_n, BinomialTable};
+use deck_analyzer::progress::Progress;
+use deck_analyzer::reader::{read_deck, read_patterns};
+use deck_analyzer::AppError;
+
+use std::hint::black_box;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// `f`を`repeat_n`回実行した時間
+fn measure(repeat_n: usize, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..repeat_n {
+        f();
+    }
+    start.elapsed()
+}
+
+/// 再帰の`permutation_n`による`combination_n`と，事前に計算した`BinomialTable`の比較
+fn main() -> Result<(), AppError> {
+    const MAX_N: u64 = 60;
+    const REPEAT_N: usize = 1000;
+
+    let recursive_time = measure(REPEAT_N, || {
+        for n in 0..=MAX_N {
+            for k in 0..=10.min(n) {
+                black_box(combination_n(black_box(n), black_box(k)));
+            }
+        }
+    });
+
+    let table = BinomialTable::new(MAX_N);
+    let table_time = measure(REPEAT_N, || {
+        for n in 0..=MAX_N {
+            for k in 0..=10.min(n) {
+                let _ = black_box(table.get(black_box(n), black_box(k)));
+            }
+        }
+    });
+
+    let build_time = measure(REPEAT_N, || {
+        black_box(BinomialTable::new(black_box(MAX_N)));
+    });
+
+    println!("nCk (n <= {MAX_N}, k <= 10) x {REPEAT_N}");
+    println!("recursive: {recursive_time:?}");
+    println!("table:     {table_time:?}");
+    println!("build table: {:?} / table", build_time / REPEAT_N as u32);
+
+    // サンプルのデッキでの全探索．表をその都度作る場合と使い回す場合
+    let deck = read_deck(Path::new("sample_deck.json"))?;
+    let init_patterns = read_patterns(Path::new("sample_init_pattern.json"))?;
+    let table = BinomialTable::new(MAX_N);
+
+    // 計測の前に一度実行し，デッキやパターンが不正な場合はエラーを返す
+    all_search_v2::all_search_pattern(&deck, &init_patterns, 5, &table, &mut Progress::silent())?;
+
+    let search_time = measure(3, || {
+        let _ = black_box(all_search_v2::all_search_pattern(
+            &deck,
+            &init_patterns,
+            5,
+            &BinomialTable::new(deck.size() as u64),
+            &mut Progress::silent(),
+        ));
+    });
+    let search_with_table_time = measure(3, || {
+        let _ = black_box(all_search_v2::all_search_pattern(
+            &deck,
+            &init_patterns,
+            5,
+            &table,
+            &mut Progress::silent(),
+        ));
+    });
+
+    println!("\nall_search_v2 (sample_deck.json, 5 cards) x 3");
+    println!("build table per search: {search_time:?}");
+    println!("shared table:           {search_with_table_time:?}");
+
+    Ok(())
+}
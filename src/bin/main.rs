@@ -1 +1,583 @@
-fn main() {}
+use deck_analyzer::all_search_v1;
+use deck_analyzer::all_search_v2;
+use deck_analyzer::analysis::{compare_decks, matchup_search, CardDiff, ProbDelta};
+use deck_analyzer::combinations::BinomialTable;
+use deck_analyzer::domain::{
+    extra_issues, CardDatabase, CardGroup, Deck, InitialPattern, SidingPlan,
+};
+use deck_analyzer::output::{AnalysisResult, Engine, Setting};
+use deck_analyzer::probability::{
+    pattern_prob, pattern_prob_log, Hypergeometric, MultivariateHypergeometric,
+};
+use deck_analyzer::reader::{read_card_database, read_deck, read_json_list, read_patterns};
+use deck_analyzer::report::Report;
+use deck_analyzer::sampling::sampling_pattern;
+use deck_analyzer::AppError;
+
+use deck_analyzer::progress::{Progress, ProgressObserver};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+usage:
+    main search <deck.json> <init_pattern.json> [--draw N] [--engine ENGINE] [--trial N]
+                [--format text|json|csv] [--output FILE]
+    main compare <old_deck.json> <new_deck.json> <init_pattern.json> [--draw N]
+    main matchup <deck.json> <siding_plan.json> <init_pattern.json> [--draw N]
+    main report <deck.json> <init_pattern.json> [--format markdown|html] [--title TITLE]
+                [--output FILE]
+    main query <deck.json> <card_group.json> [--draw N] [--condition RANGES]
+                (RANGES: comma-separated per group, e.g. \"2-,1\" = 2 or more and exactly 1)
+    common options:
+        --db FILE    card database (.json or .csv) to resolve card names and passcodes in decks";
+
+/// 全探索の進捗をターミナルのプログレスバーで表示する．
+struct ProgressBarObserver {
+    bar: Option<ProgressBar>,
+}
+
+impl ProgressObserver for ProgressBarObserver {
+    /// 複数の探索で使う場合は，探索が終わるたびに新しいプログレスバーを表示する．
+    fn on_progress(&mut self, done: u64, total: u64) {
+        let bar = match self.bar.take() {
+            Some(bar) if !bar.is_finished() && bar.length() == Some(total) => bar,
+            _ => ProgressBar::new(total).with_style(ProgressStyle::default_bar()),
+        };
+        bar.set_position(done);
+        self.bar = Some(bar);
+    }
+
+    fn on_finish(&mut self) {
+        if let Some(bar) = self.bar.as_ref() {
+            bar.finish();
+        }
+    }
+}
+
+/// 位置引数と`--key value`形式のオプション
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    /// `--db`で指定したカードデータベース
+    database: Option<CardDatabase>,
+}
+
+impl Args {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, AppError> {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(key) => {
+                    let value = args.next().ok_or_else(|| {
+                        AppError::InvalidDataError(format!("Missing value of --{key}"))
+                    })?;
+                    options.insert(key.to_string(), value);
+                }
+                None => positional.push(arg),
+            }
+        }
+
+        let database = options
+            .get("db")
+            .map(|db| read_card_database(Path::new(db)))
+            .transpose()?;
+
+        Ok(Self {
+            positional,
+            options,
+            database,
+        })
+    }
+
+    /// `i`番目の位置引数をパスとして取得する．
+    fn path(&self, i: usize) -> Result<PathBuf, AppError> {
+        self.positional
+            .get(i)
+            .map(PathBuf::from)
+            .ok_or_else(|| AppError::InvalidDataError(USAGE.to_string()))
+    }
+
+    /// `i`番目の位置引数のデッキを読み込む．カードデータベースがある場合はカード名と情報を補完する．
+    fn deck(&self, i: usize) -> Result<Deck, AppError> {
+        let deck = read_deck(&self.path(i)?)?;
+        match &self.database {
+            Some(database) => database.enrich_deck(&deck),
+            None => Ok(deck),
+        }
+    }
+
+    /// ドロー数．デフォルトは5枚
+    fn draw_n(&self) -> Result<u64, AppError> {
+        match self.options.get("draw") {
+            Some(draw_n) => draw_n
+                .parse()
+                .map_err(|_| AppError::InvalidDataError(format!("Invalid --draw: {draw_n}"))),
+            None => Ok(5),
+        }
+    }
+
+    /// 計算に用いるエンジン．デフォルトは`all_search_v2`．`exact`の場合は既約分数も表示する
+    fn engine(&self) -> Result<Engine, AppError> {
+        match self.options.get("engine").map(String::as_str) {
+            Some("probability") => Ok(Engine::Probability),
+            Some("log_probability") => Ok(Engine::LogProbability),
+            Some("all_search_v1") => Ok(Engine::AllSearchV1),
+            Some("all_search_v2") | None => Ok(Engine::AllSearchV2),
+            Some("sampling") => Ok(Engine::Sampling),
+            Some("exact") => Ok(Engine::Exact),
+            Some(engine) => Err(AppError::InvalidDataError(format!(
+                "Invalid --engine: {engine}"
+            ))),
+        }
+    }
+
+    /// ランダムドローの試行回数．デフォルトは100000回
+    fn trial_n(&self) -> Result<usize, AppError> {
+        match self.options.get("trial") {
+            Some(trial_n) => trial_n
+                .parse()
+                .map_err(|_| AppError::InvalidDataError(format!("Invalid --trial: {trial_n}"))),
+            None => Ok(100000),
+        }
+    }
+}
+
+/// 既約分数の表示．厳密な計算以外では表示しない
+fn format_fraction(fraction: &Option<String>) -> String {
+    match fraction {
+        Some(fraction) => format!(", fraction: {fraction}"),
+        None => String::new(),
+    }
+}
+
+/// 計算結果を`--format`の形式で`--output`のファイルか標準出力に書き込む．
+fn write_result(args: &Args, result: &AnalysisResult) -> Result<(), AppError> {
+    let format = args.options.get("format").map_or("text", String::as_str);
+    if !["text", "json", "csv"].contains(&format) {
+        return Err(AppError::InvalidDataError(format!(
+            "Invalid --format: {format}"
+        )));
+    }
+
+    let mut writer: Box<dyn Write> = match args.options.get("output") {
+        Some(output) => Box::new(File::create(output)?),
+        None => Box::new(std::io::stdout().lock()),
+    };
+
+    match format {
+        "json" => result.write_json(&mut writer)?,
+        "csv" => result.write_csv(&mut writer)?,
+        _ => {
+            writeln!(writer, "pattern prob")?;
+            for pat in result.patterns.iter() {
+                writeln!(
+                    writer,
+                    "name: {}, level: {}, prob: {:.8}{}",
+                    pat.name,
+                    pat.level,
+                    pat.prob,
+                    format_fraction(&pat.fraction)
+                )?;
+            }
+            writeln!(writer, "level prob")?;
+            for level in result.levels.iter() {
+                writeln!(
+                    writer,
+                    "level: {}, prob: {:.8}{}",
+                    level.level,
+                    level.prob,
+                    format_fraction(&level.fraction)
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// エクストラデッキのカードの差分の表示
+fn format_section(diff: &CardDiff) -> &'static str {
+    if diff.extra {
+        " (extra)"
+    } else {
+        ""
+    }
+}
+
+/// 確率の差分の表示．相対差分は比較元の確率が0の場合は表示しない
+fn format_delta(delta: &ProbDelta) -> String {
+    let rel_delta = match delta.rel_delta {
+        Some(rel_delta) => format!("{:+.2}%", rel_delta * 100.0),
+        None => "-".to_string(),
+    };
+    format!(
+        "old: {:.8}, new: {:.8}, delta: {:+.8} ({})",
+        delta.old_prob, delta.new_prob, delta.abs_delta, rel_delta
+    )
+}
+
+/// エクストラデッキのカードが足りずにマッチしないパターンを警告する．
+fn warn_extra(deck: &Deck, patterns: &[InitialPattern]) {
+    for issue in extra_issues(deck, patterns) {
+        eprintln!("warning: {issue}");
+    }
+}
+
+fn search(args: &Args) -> Result<(), AppError> {
+    let deck = args.deck(1)?;
+    let init_patterns = read_patterns(&args.path(2)?)?;
+    warn_extra(&deck, &init_patterns);
+
+    let draw_n = args.draw_n()?;
+    let engine = args.engine()?;
+    let mut setting = Setting {
+        draw_n,
+        trial_n: None,
+    };
+    let table = BinomialTable::new(deck.size() as u64);
+
+    let result = match engine {
+        Engine::Probability => {
+            let pattern_probs = init_patterns
+                .iter()
+                .map(|pat| pattern_prob(&deck, pat, draw_n, &table))
+                .collect::<Result<Vec<_>, _>>()?;
+            AnalysisResult::from_pattern_probs(
+                engine,
+                &deck,
+                &init_patterns,
+                setting,
+                &pattern_probs,
+            )
+        }
+        Engine::LogProbability => {
+            let pattern_probs = init_patterns
+                .iter()
+                .map(|pat| pattern_prob_log(&deck, pat, draw_n))
+                .collect::<Result<Vec<_>, _>>()?;
+            AnalysisResult::from_pattern_probs(
+                engine,
+                &deck,
+                &init_patterns,
+                setting,
+                &pattern_probs,
+            )
+        }
+        Engine::AllSearchV1 => {
+            let result = all_search_v1::all_search_pattern(
+                &deck,
+                &init_patterns,
+                draw_n,
+                &mut Progress::new(&mut ProgressBarObserver { bar: None }),
+            )?;
+            AnalysisResult::new(engine, &deck, &init_patterns, setting, &result)
+        }
+        Engine::AllSearchV2 => {
+            let result = all_search_v2::all_search_pattern(
+                &deck,
+                &init_patterns,
+                draw_n,
+                &table,
+                &mut Progress::new(&mut ProgressBarObserver { bar: None }),
+            )?;
+            AnalysisResult::new(engine, &deck, &init_patterns, setting, &result)
+        }
+        Engine::Sampling => {
+            let trial_n = args.trial_n()?;
+            setting.trial_n = Some(trial_n);
+            let result = sampling_pattern(
+                &deck,
+                &init_patterns,
+                draw_n as usize,
+                trial_n,
+                &mut Progress::new(&mut ProgressBarObserver { bar: None }),
+            )?;
+            AnalysisResult::new(engine, &deck, &init_patterns, setting, &result)
+        }
+        Engine::Exact => {
+            let result = all_search_v2::all_search_pattern_exact(
+                &deck,
+                &init_patterns,
+                draw_n,
+                &mut Progress::new(&mut ProgressBarObserver { bar: None }),
+            )?;
+            AnalysisResult::from_exact(&deck, &init_patterns, setting, &result)
+        }
+    };
+
+    write_result(args, &result)
+}
+
+fn compare(args: &Args) -> Result<(), AppError> {
+    let old_deck = args.deck(1)?;
+    let new_deck = args.deck(2)?;
+    let init_patterns = read_patterns(&args.path(3)?)?;
+    warn_extra(&old_deck, &init_patterns);
+    warn_extra(&new_deck, &init_patterns);
+
+    let result = compare_decks(
+        &old_deck,
+        &new_deck,
+        &init_patterns,
+        args.draw_n()?,
+        &mut Progress::new(&mut ProgressBarObserver { bar: None }),
+    )?;
+
+    println!("card diff");
+    for diff in result.card_diff.iter() {
+        println!(
+            "name: {}{}, old: {}, new: {}, delta: {:+}",
+            diff.name,
+            format_section(diff),
+            diff.old_number,
+            diff.new_number,
+            diff.new_number as i16 - diff.old_number as i16
+        );
+    }
+
+    println!("pattern prob");
+    for (pat, delta) in init_patterns.iter().zip(result.pattern_deltas().iter()) {
+        let InitialPattern { name, level, .. } = pat;
+        println!("name: {}, level: {}, {}", name, level, format_delta(delta));
+    }
+
+    println!("level prob");
+    for (i, delta) in result.level_deltas().iter().enumerate() {
+        println!("level: {}, {}", i, format_delta(delta));
+    }
+
+    Ok(())
+}
+
+/// 1戦目とサイドチェンジの計画ごとのレベルの確率を表にして表示する．
+fn matchup(args: &Args) -> Result<(), AppError> {
+    let deck = args.deck(1)?;
+    let plans = read_json_list::<SidingPlan>(&args.path(2)?)?;
+    let init_patterns = read_patterns(&args.path(3)?)?;
+    warn_extra(&deck, &init_patterns);
+
+    let table = matchup_search(
+        &deck,
+        &plans,
+        &init_patterns,
+        args.draw_n()?,
+        &mut Progress::new(&mut ProgressBarObserver { bar: None }),
+    )?;
+
+    let levels = (1..table.game1.level_result.len())
+        .map(|level| format!(" | level {level}"))
+        .collect::<String>();
+    println!("| plan | in | out{levels} |");
+    println!(
+        "|---|---|---{}|",
+        "|---".repeat(table.game1.level_result.len() - 1)
+    );
+
+    let game1 = table.game1.level_result[1..]
+        .iter()
+        .map(|prob| format!(" | {prob:.4}"))
+        .collect::<String>();
+    println!("| game 1 | - | -{game1} |");
+
+    for row in table.rows.iter() {
+        let diff = |is_in: bool| {
+            row.card_diff
+                .iter()
+                .filter(|diff| (diff.new_number > diff.old_number) == is_in)
+                .map(|diff| {
+                    format!(
+                        "{}{} x{}",
+                        diff.name,
+                        format_section(diff),
+                        diff.new_number.abs_diff(diff.old_number)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let probs = table.level_deltas(row)[1..]
+            .iter()
+            .map(|delta| format!(" | {:.4} ({:+.4})", delta.new_prob, delta.abs_delta))
+            .collect::<String>();
+        println!("| {} | {} | {}{probs} |", row.plan, diff(true), diff(false));
+    }
+
+    Ok(())
+}
+
+/// `--condition`の一つのグループの枚数の範囲．`n`はちょうどn枚，`n-`はn枚以上，`-n`はn枚以下，`n-m`はn枚以上m枚以下
+fn parse_range(range: &str) -> Result<RangeInclusive<u64>, AppError> {
+    let invalid = || AppError::InvalidDataError(format!("Invalid --condition: {range}"));
+    let parse = |n: &str, default: u64| -> Result<u64, AppError> {
+        match n.trim() {
+            "" => Ok(default),
+            n => n.parse().map_err(|_| invalid()),
+        }
+    };
+
+    match range.split_once('-') {
+        Some((min, max)) => Ok(parse(min, 0)?..=parse(max, u64::MAX)?),
+        None => {
+            let n = parse(range, 0)?;
+            Ok(n..=n)
+        }
+    }
+}
+
+/// グループごとの枚数の分布と，`--condition`が指定された場合はその条件を満たす確率を表示する．
+fn query(args: &Args) -> Result<(), AppError> {
+    let deck = args.deck(1)?;
+    let groups = read_json_list::<CardGroup>(&args.path(2)?)?;
+    let draw_n = args.draw_n()?;
+
+    for group in groups.iter() {
+        let dist = Hypergeometric::from_group(&deck, group, draw_n)?;
+        println!(
+            "group: {}, cards: {}, mean: {:.8}, variance: {:.8}",
+            group.name,
+            dist.success_n,
+            dist.mean(),
+            dist.variance()
+        );
+        for (k, pmf) in dist.distribution().into_iter().enumerate() {
+            println!(
+                "k: {}, pmf: {:.8}, cdf: {:.8}, at least: {:.8}",
+                k,
+                pmf,
+                dist.cdf(k as u64),
+                dist.at_least(k as u64)
+            );
+        }
+    }
+
+    if let Some(condition) = args.options.get("condition") {
+        let ranges = condition
+            .split(',')
+            .map(parse_range)
+            .collect::<Result<Vec<_>, _>>()?;
+        if ranges.len() != groups.len() {
+            return Err(AppError::InvalidDataError(format!(
+                "--condition has {} ranges, expected {}.",
+                ranges.len(),
+                groups.len()
+            )));
+        }
+
+        let dist = MultivariateHypergeometric::from_groups(&deck, &groups, draw_n)?;
+        println!(
+            "condition: {condition}, prob: {:.8}",
+            dist.range_prob(&ranges)
+        );
+    }
+
+    Ok(())
+}
+
+/// 先攻(5枚)と後攻(6枚)を全探索し，レポートを出力する．
+fn report(args: &Args) -> Result<(), AppError> {
+    let deck = args.deck(1)?;
+    let init_patterns = read_patterns(&args.path(2)?)?;
+    warn_extra(&deck, &init_patterns);
+
+    let table = BinomialTable::new(deck.size() as u64);
+    let mut observer = ProgressBarObserver { bar: None };
+    let [first, second] = [5, 6].map(|draw_n| {
+        let mut progress = Progress::new(&mut observer);
+        all_search_v2::all_search_pattern(&deck, &init_patterns, draw_n, &table, &mut progress).map(
+            |result| {
+                AnalysisResult::new(
+                    Engine::AllSearchV2,
+                    &deck,
+                    &init_patterns,
+                    Setting {
+                        draw_n,
+                        trial_n: None,
+                    },
+                    &result,
+                )
+            },
+        )
+    });
+
+    let report = Report {
+        title: args
+            .options
+            .get("title")
+            .cloned()
+            .unwrap_or("deck-analyzer".to_string()),
+        deck: &deck,
+        patterns: &init_patterns,
+        first: &first?,
+        second: &second?,
+    };
+
+    let text = match args.options.get("format").map(String::as_str) {
+        Some("markdown") | None => report.to_markdown(),
+        Some("html") => report.to_html(),
+        Some(format) => {
+            return Err(AppError::InvalidDataError(format!(
+                "Invalid --format: {format}"
+            )))
+        }
+    };
+
+    match args.options.get("output") {
+        Some(output) => std::fs::write(output, text)?,
+        None => print!("{text}"),
+    }
+
+    Ok(())
+}
+
+/// サブコマンドを実行する．サブコマンドが無い場合や不明な場合は使い方を含むエラー
+fn run(args: &Args) -> Result<(), AppError> {
+    match args.positional.first().map(String::as_str) {
+        Some("search") => search(args),
+        Some("compare") => compare(args),
+        Some("matchup") => matchup(args),
+        Some("report") => report(args),
+        Some("query") => query(args),
+        Some(command) => Err(AppError::InvalidDataError(format!(
+            "Unknown command: {command}\n{USAGE}"
+        ))),
+        None => Err(AppError::InvalidDataError(USAGE.to_string())),
+    }
+}
+
+/// エラーはファイル名や不正な要素を含めて表示する．
+fn main() -> ExitCode {
+    match Args::parse(std::env::args().skip(1)).and_then(|args| run(&args)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{run, Args};
+
+    fn args(args: &[&str]) -> Args {
+        Args::parse(args.iter().map(|arg| arg.to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_run_unknown_command() {
+        assert!(run(&args(&[])).is_err());
+        let err = run(&args(&["serch", "deck.json"])).unwrap_err();
+        assert!(err.to_string().contains("Unknown command: serch"));
+        // 位置引数が足りない場合
+        assert!(run(&args(&["compare", "deck.json"])).is_err());
+        assert!(Args::parse(["--draw".to_string()].into_iter()).is_err());
+    }
+}
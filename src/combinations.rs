@@ -1,51 +1,264 @@
-/// nからkコ分の部分階乗つまり順列の数え上げ．
-/// カードゲームの最初のドロー数はたかだか10枚以下であるため，その10枚程度掛け算を行えばよく、
-/// 階乗がオーバーフローするような値だとしても順列を計算できる．
-fn permutation_n(n: u64, k: u64) -> Option<u64> {
-    if n < k {
-        // 掛ける回数が元の数より増えてしまう場合
-        None
-    } else {
-        match k {
-            // 掛ける回数が0の場合
-            0 => Some(1),
-            // 掛ける回数が1の場合，そのまま返す
-            1 => Some(n),
-            _ => match n {
-                // 0の階乗は1
-                0 => Some(1),
-                // 1の階乗は1
-                1 => Some(1),
-                // 再帰
-                _ => permutation_n(n - 1, k - 1).and_then(|next| next.checked_mul(n)),
-            },
-        }
-    }
-}
-
-/// nCkの組み合わせ．部分階乗(順列)を用いて計算する．kか(n-k)の小さい方を用いて計算する．
-pub fn combination_n(n: u64, k: u64) -> Option<u64> {
-    let k = std::cmp::min(k, n.checked_sub(k)?); // kかn-kの小さい方を新しくkとする．
-    match (permutation_n(n, k), permutation_n(k, k)) {
-        // どちらもオーバーフローしていない場合
-        (Some(den), Some(num)) => den.checked_div(num),
-        // どちらかがオーバーフローしている場合
-        (_, _) => None,
-    }
-}
-
-#[cfg(test)]
-mod test {
-    use super::{combination_n, permutation_n};
-
-    #[test]
-    fn test_p() {
-        assert_eq!(permutation_n(40, 5), Some(78960960));
-    }
-
-    #[test]
-    fn test_c() {
-        assert_eq!(combination_n(40, 5), Some(658008));
-        assert_eq!(combination_n(40, 35), Some(658008));
-    }
-}
+use crate::AppError;
+
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
+/// `population_n`枚のデッキから`draw_n`枚引けるかどうかを検証する．引けない場合は場合の数が0となり確率を求められないためエラー
+pub fn validate_draw_n(population_n: u64, draw_n: u64) -> Result<(), AppError> {
+    if draw_n > population_n {
+        return Err(AppError::InvalidDataError(format!(
+            "Cannot draw {draw_n} cards from deck ({population_n})."
+        )));
+    }
+    Ok(())
+}
+
+/// nからkコ分の部分階乗つまり順列の数え上げ．
+/// カードゲームの最初のドロー数はたかだか10枚以下であるため，その10枚程度掛け算を行えばよく、
+/// 階乗がオーバーフローするような値だとしても順列を計算できる．
+fn permutation_n(n: u64, k: u64) -> Option<u64> {
+    if n < k {
+        // 掛ける回数が元の数より増えてしまう場合
+        None
+    } else {
+        match k {
+            // 掛ける回数が0の場合
+            0 => Some(1),
+            // 掛ける回数が1の場合，そのまま返す
+            1 => Some(n),
+            _ => match n {
+                // 0の階乗は1
+                0 => Some(1),
+                // 1の階乗は1
+                1 => Some(1),
+                // 再帰
+                _ => permutation_n(n - 1, k - 1).and_then(|next| next.checked_mul(n)),
+            },
+        }
+    }
+}
+
+/// nCkの組み合わせ．部分階乗(順列)を用いて計算する．kか(n-k)の小さい方を用いて計算する．
+pub fn combination_n(n: u64, k: u64) -> Option<u64> {
+    let k = std::cmp::min(k, n.checked_sub(k)?); // kかn-kの小さい方を新しくkとする．
+    match (permutation_n(n, k), permutation_n(k, k)) {
+        // どちらもオーバーフローしていない場合
+        (Some(den), Some(num)) => den.checked_div(num),
+        // どちらかがオーバーフローしている場合
+        (_, _) => None,
+    }
+}
+
+/// 多倍長整数によるnCkの組み合わせ．オーバーフローしない．n < kの場合は0．
+pub fn combination_big(n: u64, k: u64) -> BigUint {
+    if n < k {
+        return BigUint::zero();
+    }
+    let k = std::cmp::min(k, n - k); // kかn-kの小さい方を新しくkとする．
+
+    // 途中の値は常にC(n - k + i, i)であるため割り切れる
+    (1..=k).fold(BigUint::one(), |acc, i| acc * (n - k + i) / i)
+}
+
+/// ガンマ関数の自然対数．Lanczos近似(g = 7, n = 9)で計算する．
+pub fn ln_gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // 相反公式 Γ(x)Γ(1 - x) = π / sin(πx)
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin())
+            .abs()
+            .ln()
+            - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + G + 0.5;
+    let sum = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .skip(1)
+        .fold(COEFFICIENTS[0], |acc, (i, c)| acc + c / (x + i as f64));
+
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// nCkの自然対数．対数ガンマ関数で計算するためオーバーフローしない．n < kの場合は負の無限大．
+pub fn ln_combination(n: u64, k: u64) -> f64 {
+    if n < k {
+        return f64::NEG_INFINITY;
+    }
+    ln_gamma(n as f64 + 1.0) - ln_gamma(k as f64 + 1.0) - ln_gamma((n - k) as f64 + 1.0)
+}
+
+/// 超幾何分布の確率質量関数．`success_n`枚を含む`population_n`枚のデッキから`draw_n`枚引いたとき，
+/// ちょうど`k`枚含まれる確率．
+pub fn hypergeometric_pmf(population_n: u64, success_n: u64, draw_n: u64, k: u64) -> f64 {
+    if success_n > population_n || draw_n > population_n || k > draw_n {
+        return 0.0;
+    }
+    (ln_combination(success_n, k) + ln_combination(population_n - success_n, draw_n - k)
+        - ln_combination(population_n, draw_n))
+    .exp()
+}
+
+/// 超幾何分布の累積分布関数．`k`枚以下含まれる確率．
+pub fn hypergeometric_cdf(population_n: u64, success_n: u64, draw_n: u64, k: u64) -> f64 {
+    let cdf = (0..=k)
+        .map(|i| hypergeometric_pmf(population_n, success_n, draw_n, i))
+        .sum::<f64>();
+    cdf.min(1.0)
+}
+
+/// パスカルの三角形で事前に計算したnCkの表．分析ごとにデッキの枚数まで一度だけ作成し，
+/// 全探索の度に`combination_n`で計算し直さないようにする．
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinomialTable {
+    /// `rows[n][k]`がnCk．u128でオーバーフローする場合はNone
+    rows: Vec<Vec<Option<u128>>>,
+}
+
+impl BinomialTable {
+    /// nが`max_n`以下の全てのnCkを計算する．
+    pub fn new(max_n: u64) -> Self {
+        let mut rows: Vec<Vec<Option<u128>>> = Vec::with_capacity(max_n as usize + 1);
+        rows.push(vec![Some(1)]);
+        for n in 1..=max_n as usize {
+            let prev = &rows[n - 1];
+            let row = (0..=n)
+                .map(|k| match k {
+                    0 => Some(1),
+                    _ if k == n => Some(1),
+                    _ => prev[k - 1].zip(prev[k]).and_then(|(x, y)| x.checked_add(y)),
+                })
+                .collect();
+            rows.push(row);
+        }
+
+        Self { rows }
+    }
+
+    /// 表に含まれるnの最大値
+    pub fn max_n(&self) -> u64 {
+        self.rows.len() as u64 - 1
+    }
+
+    /// nCk．n < kの場合は0．nが表の範囲外の場合と，u128でオーバーフローしている場合はそれぞれ別のエラー
+    pub fn get(&self, n: u64, k: u64) -> Result<u128, AppError> {
+        let row = self
+            .rows
+            .get(n as usize)
+            .ok_or(AppError::BinomialTableRangeError {
+                n,
+                max_n: self.max_n(),
+            })?;
+        if n < k {
+            Ok(0)
+        } else {
+            row[k as usize].ok_or(AppError::OverflowCombinationError)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        combination_big, combination_n, hypergeometric_cdf, hypergeometric_pmf, ln_combination,
+        permutation_n, validate_draw_n, BinomialTable,
+    };
+    use crate::AppError;
+
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_p() {
+        assert_eq!(permutation_n(40, 5), Some(78960960));
+    }
+
+    #[test]
+    fn test_c() {
+        assert_eq!(combination_n(40, 5), Some(658008));
+        assert_eq!(combination_n(40, 35), Some(658008));
+    }
+
+    #[test]
+    fn test_c_big() {
+        assert_eq!(combination_big(40, 5), BigUint::from(658008_u64));
+        assert_eq!(combination_big(5, 6), BigUint::from(0_u64));
+        assert_eq!(
+            combination_big(100, 50).to_string(),
+            "100891344545564193334812497256"
+        );
+    }
+
+    #[test]
+    fn test_ln_combination() {
+        assert!((ln_combination(40, 5) - 658008_f64.ln()).abs() < 1e-10);
+        assert!((ln_combination(100, 50) - 1.0089134454556419e29_f64.ln()).abs() < 1e-10);
+        assert_eq!(ln_combination(5, 6), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_hypergeometric() {
+        // 40枚中3枚のカードを5枚引いて1枚も引かない確率: 37C5 / 40C5
+        assert!((hypergeometric_pmf(40, 3, 5, 0) - 435897.0 / 658008.0).abs() < 1e-12);
+        assert_eq!(hypergeometric_pmf(40, 3, 5, 4), 0.0);
+        assert!((hypergeometric_cdf(40, 3, 5, 5) - 1.0).abs() < 1e-12);
+        assert!(
+            (hypergeometric_cdf(40, 3, 5, 1)
+                - hypergeometric_pmf(40, 3, 5, 0)
+                - hypergeometric_pmf(40, 3, 5, 1))
+            .abs()
+                < 1e-15
+        );
+    }
+
+    #[test]
+    fn test_validate_draw_n() {
+        assert!(validate_draw_n(40, 40).is_ok());
+        assert!(validate_draw_n(40, 41).is_err());
+        assert!(validate_draw_n(0, 0).is_ok());
+    }
+
+    #[test]
+    fn test_binomial_table() {
+        let table = BinomialTable::new(130);
+        assert_eq!(table.max_n(), 130);
+        assert_eq!(table.get(40, 5).unwrap(), 658008);
+        assert_eq!(table.get(40, 35).unwrap(), 658008);
+        assert_eq!(table.get(5, 6).unwrap(), 0);
+        assert!(matches!(
+            table.get(131, 1),
+            Err(AppError::BinomialTableRangeError { n: 131, max_n: 130 })
+        ));
+        assert_eq!(
+            table.get(100, 50).unwrap().to_string(),
+            combination_big(100, 50).to_string()
+        );
+        for n in 0..=60 {
+            for k in 0..=n {
+                if let Some(c) = combination_n(n, k) {
+                    assert_eq!(table.get(n, k).unwrap(), c as u128);
+                }
+            }
+        }
+
+        // u128でオーバーフローする場合
+        let large_table = BinomialTable::new(140);
+        assert!(matches!(
+            large_table.get(140, 70),
+            Err(AppError::OverflowCombinationError)
+        ));
+    }
+}
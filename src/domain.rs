@@ -1,101 +1,382 @@
-mod counter_set;
-mod string_or_list;
-
-/// データ構造をまとめておく
-pub mod data_structure {
-    pub use super::counter_set::CounterSet;
-}
-
-pub use string_or_list::StringOrList;
-
-use serde::Deserialize;
-
-#[derive(Deserialize, Clone, PartialEq, Eq, Debug)]
-pub struct Card {
-    pub name: String,
-    pub number: u8,
-}
-
-#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Hash)]
-pub struct InitialPattern {
-    pub name: String,
-    pub first: StringOrList,
-    pub second: Option<StringOrList>,
-    pub third: Option<StringOrList>,
-    pub level: u8,
-}
-
-#[cfg(test)]
-mod test {
-    use super::{Card, InitialPattern, StringOrList};
-
-    #[test]
-    fn deserialize() {
-        {
-            let json_str = r#"
-            [
-                {
-                    "name": "溟界の滓－ヌル",
-                    "number": 3
-                },
-                {
-                    "name": "溟界の滓－ナイア",
-                    "number": 3
-                }
-            ]
-            "#;
-
-            assert_eq!(
-                serde_json::from_str::<Vec<Card>>(&json_str).unwrap(),
-                vec![
-                    Card {
-                        name: "溟界の滓－ヌル".to_string(),
-                        number: 3
-                    },
-                    Card {
-                        name: "溟界の滓－ナイア".to_string(),
-                        number: 3
-                    }
-                ]
-            )
-        }
-
-        {
-            let json_str = r#"
-            [
-                {
-                    "name": "ヌル＋任意",
-                    "first": "溟界の滓－ヌル",
-                    "level": 1
-                },
-                {
-                    "name": "ヌル＋任意＋陰の光",
-                    "first": "溟界の滓－ヌル",
-                    "second": "陰の光",
-                    "level": 2
-                }
-            ]
-            "#;
-
-            assert_eq!(
-                serde_json::from_str::<Vec<InitialPattern>>(&json_str).unwrap(),
-                vec![
-                    InitialPattern {
-                        name: "ヌル＋任意".to_string(),
-                        first: StringOrList::String("溟界の滓－ヌル".to_string()),
-                        second: None,
-                        third: None,
-                        level: 1
-                    },
-                    InitialPattern {
-                        name: "ヌル＋任意＋陰の光".to_string(),
-                        first: StringOrList::String("溟界の滓－ヌル".to_string()),
-                        second: Some(StringOrList::String("陰の光".to_string())),
-                        third: None,
-                        level: 2
-                    }
-                ]
-            )
-        }
-    }
-}
+mod card_database;
+mod card_id;
+mod card_query;
+mod deck;
+mod effect;
+mod siding;
+mod string_or_list;
+
+pub use card_database::{CardDatabase, CardEntry, CardRow};
+pub use card_id::{fill_counts, CardId};
+pub use card_query::{is_query, CardQuery, Comparison, Condition, QUERY_PREFIX};
+pub use deck::{
+    Deck, DeckFile, DeckSections, EXTRA_DECK_MAX, MAIN_DECK_MAX, MAIN_DECK_MIN, SIDE_DECK_MAX,
+};
+pub use effect::{CardEffect, EffectAction};
+pub use siding::{SideCard, SidingPlan};
+pub use string_or_list::StringOrList;
+
+use crate::{AppError, EntryIssue};
+
+use itertools::Itertools;
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Default, Hash)]
+pub struct Card {
+    pub name: String,
+    pub number: u8,
+    /// 手札で発動する効果．サンプリングの際にパターンの判定前に処理する．
+    #[serde(default)]
+    pub effects: Vec<CardEffect>,
+    /// デッキに残っているこれらのカードのいずれか一枚として扱う(サーチカード)．全探索とサンプリングのパターンの判定で用いる．
+    #[serde(default)]
+    pub acts_as: Option<StringOrList>,
+    /// カードの種類や種族などの情報．パターンやグループのクエリで用いる．
+    #[serde(flatten)]
+    pub meta: CardMeta,
+}
+
+/// カードの種類や種族などの情報．全て省略でき，クエリで用いない値も読み込める．
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Default, Hash)]
+pub struct CardMeta {
+    /// カードの種類(例: モンスター，魔法，罠)
+    #[serde(rename = "type")]
+    pub card_type: Option<String>,
+    /// 属性(例: 闇)
+    pub attribute: Option<String>,
+    /// 種族(例: 爬虫類)
+    pub race: Option<String>,
+    /// レベルまたはランク
+    pub level: Option<u8>,
+    /// 任意のタグ(例: 初動，手札誘発)
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct InitialPattern {
+    pub name: String,
+    pub first: StringOrList,
+    pub second: Option<StringOrList>,
+    pub third: Option<StringOrList>,
+    pub level: u8,
+    /// エクストラデッキに全て登録されている必要があるカード．登録されていない場合はマッチしない．
+    pub extra: Option<StringOrList>,
+}
+
+/// 名前を付けたカードのまとまり(例: 手札誘発，初動)
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct CardGroup {
+    pub name: String,
+    pub cards: StringOrList,
+}
+
+/// マリガン(引き直し)の方法
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Hash)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MulliganMethod {
+    /// 手札を全てデッキに戻してシャッフルし，同じ枚数を引き直す
+    ShuffleBack,
+    /// 手札を全てデッキに戻してシャッフルし，同じ枚数を引き直した後に`bottom_n`枚をデッキの下に置く
+    London { bottom_n: u8 },
+}
+
+/// マリガンのルール．最初の手札が`level`以上のパターンにマッチしない場合に一度だけ引き直す．
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct MulliganPolicy {
+    pub level: u8,
+    pub method: MulliganMethod,
+}
+
+impl InitialPattern {
+    /// first, second, thirdのうち指定されているものを順に取得する．
+    pub fn slots(&self) -> Result<Vec<&StringOrList>, AppError> {
+        match (&self.second, &self.third) {
+            (None, None) => Ok(vec![&self.first]),
+            (Some(second), None) => Ok(vec![&self.first, second]),
+            (Some(second), Some(third)) => Ok(vec![&self.first, second, third]),
+            (None, Some(_)) => Err(AppError::InvalidDataError(format!(
+                "Invalid init_pattern {}: third is set without second.",
+                self.name
+            ))),
+        }
+    }
+
+    /// パターンの不正な内容を全て取得する．`index`はパターンの一覧の中のインデックス
+    pub fn issues(&self, index: usize) -> Vec<EntryIssue> {
+        let issue = |field: &str, message: &str| EntryIssue {
+            index,
+            name: Some(self.name.to_string()),
+            field: Some(field.to_string()),
+            message: message.to_string(),
+        };
+
+        let mut issues = Vec::new();
+        if self.second.is_none() && self.third.is_some() {
+            issues.push(issue("third", "third is set without second."));
+        }
+        for (field, slot) in [
+            ("first", Some(&self.first)),
+            ("second", self.second.as_ref()),
+            ("third", self.third.as_ref()),
+            ("extra", self.extra.as_ref()),
+        ] {
+            match slot {
+                Some(StringOrList::List(list)) if list.is_empty() => {
+                    issues.push(issue(field, "empty list."));
+                }
+                Some(slot) if slot.as_vec().iter().any(|name| name.is_empty()) => {
+                    issues.push(issue(field, "empty card name."));
+                }
+                // エクストラデッキのカードはカード名で判定するため，クエリは使えない
+                Some(slot)
+                    if field == "extra" && slot.as_vec().iter().any(|name| is_query(name)) =>
+                {
+                    issues.push(issue(field, "query is not supported in extra."));
+                }
+                Some(slot) => {
+                    for name in slot.as_vec().into_iter().filter(|name| is_query(name)) {
+                        if let Err(err) = name.parse::<CardQuery>() {
+                            issues.push(issue(field, &err.to_string()));
+                        }
+                    }
+                }
+                None => {}
+            }
+        }
+        issues
+    }
+
+    /// 必要なエクストラデッキのカードが全て`deck`のエクストラデッキに登録されているかどうか
+    pub fn is_live(&self, deck: &Deck) -> bool {
+        self.extra
+            .as_ref()
+            .is_none_or(|extra| extra.as_vec().iter().all(|name| deck.in_extra(name)))
+    }
+}
+
+/// 初動パターンを全て検証し，最大のレベルを返す．不正なパターンがある場合は全てまとめてエラーとする．
+pub fn validate_patterns(patterns: &[InitialPattern]) -> Result<u8, AppError> {
+    let issues = patterns
+        .iter()
+        .enumerate()
+        .flat_map(|(index, pat)| pat.issues(index))
+        .collect::<Vec<_>>();
+    if !issues.is_empty() {
+        return Err(AppError::InvalidPatternError(issues));
+    }
+
+    patterns
+        .iter()
+        .map(|pat| pat.level)
+        .max()
+        .ok_or(AppError::EmptyPatternError)
+}
+
+/// エクストラデッキに登録されていないカードを必要とするパターンを全て取得する．これらのパターンはマッチしないが，
+/// エラーにはせず分析を続ける．
+pub fn extra_issues(deck: &Deck, patterns: &[InitialPattern]) -> Vec<EntryIssue> {
+    patterns
+        .iter()
+        .enumerate()
+        .filter_map(|(index, pat)| {
+            let missing = pat
+                .extra
+                .as_ref()?
+                .as_vec()
+                .into_iter()
+                .filter(|name| !deck.in_extra(name))
+                .join(", ");
+            (!missing.is_empty()).then(|| EntryIssue {
+                index,
+                name: Some(pat.name.to_string()),
+                field: Some("extra".to_string()),
+                message: format!("not in extra deck: {missing}."),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{extra_issues, validate_patterns, Card, Deck, InitialPattern, StringOrList};
+    use crate::AppError;
+
+    #[test]
+    fn deserialize() {
+        {
+            let json_str = r#"
+            [
+                {
+                    "name": "溟界の滓－ヌル",
+                    "number": 3
+                },
+                {
+                    "name": "溟界の滓－ナイア",
+                    "number": 3
+                }
+            ]
+            "#;
+
+            assert_eq!(
+                serde_json::from_str::<Vec<Card>>(json_str).unwrap(),
+                vec![
+                    Card {
+                        name: "溟界の滓－ヌル".to_string(),
+                        number: 3,
+                        ..Default::default()
+                    },
+                    Card {
+                        name: "溟界の滓－ナイア".to_string(),
+                        number: 3,
+                        ..Default::default()
+                    }
+                ]
+            )
+        }
+
+        {
+            let json_str = r#"
+            [
+                {
+                    "name": "ヌル＋任意",
+                    "first": "溟界の滓－ヌル",
+                    "level": 1
+                },
+                {
+                    "name": "ヌル＋任意＋陰の光",
+                    "first": "溟界の滓－ヌル",
+                    "second": "陰の光",
+                    "level": 2
+                }
+            ]
+            "#;
+
+            assert_eq!(
+                serde_json::from_str::<Vec<InitialPattern>>(json_str).unwrap(),
+                vec![
+                    InitialPattern {
+                        name: "ヌル＋任意".to_string(),
+                        first: StringOrList::String("溟界の滓－ヌル".to_string()),
+                        second: None,
+                        third: None,
+                        level: 1,
+                        extra: None
+                    },
+                    InitialPattern {
+                        name: "ヌル＋任意＋陰の光".to_string(),
+                        first: StringOrList::String("溟界の滓－ヌル".to_string()),
+                        second: Some(StringOrList::String("陰の光".to_string())),
+                        third: None,
+                        level: 2,
+                        extra: None
+                    }
+                ]
+            )
+        }
+    }
+
+    #[test]
+    fn test_validate_patterns() {
+        let valid = InitialPattern {
+            name: "ヌル".to_string(),
+            first: StringOrList::String("溟界の滓－ヌル".to_string()),
+            second: None,
+            third: None,
+            level: 2,
+            extra: None,
+        };
+        let invalid = vec![
+            valid.clone(),
+            InitialPattern {
+                name: "third only".to_string(),
+                third: Some(StringOrList::String("溟界の滓－ヌル".to_string())),
+                ..valid.clone()
+            },
+            InitialPattern {
+                name: "empty".to_string(),
+                first: StringOrList::List(Vec::new()),
+                ..valid.clone()
+            },
+            InitialPattern {
+                name: "query".to_string(),
+                first: StringOrList::String("@color=黒".to_string()),
+                ..valid.clone()
+            },
+            InitialPattern {
+                name: "extra query".to_string(),
+                extra: Some(StringOrList::String("@type=リンク".to_string())),
+                ..valid.clone()
+            },
+        ];
+
+        assert_eq!(validate_patterns(&[valid]).unwrap(), 2);
+        assert!(matches!(
+            validate_patterns(&[]),
+            Err(AppError::EmptyPatternError)
+        ));
+
+        match validate_patterns(&invalid) {
+            Err(AppError::InvalidPatternError(issues)) => {
+                let issues = issues
+                    .iter()
+                    .map(|issue| (issue.index, issue.field.as_deref()))
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    issues,
+                    vec![
+                        (1, Some("third")),
+                        (2, Some("first")),
+                        (3, Some("first")),
+                        (4, Some("extra"))
+                    ]
+                );
+            }
+            result => panic!("unexpected result: {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_extra_issues() {
+        let deck = Deck::new(vec![Card {
+            name: "溟界の滓－ヌル".to_string(),
+            number: 3,
+            ..Default::default()
+        }])
+        .unwrap()
+        .with_extra(vec![Card {
+            name: "I:Pマスカレーナ".to_string(),
+            number: 1,
+            ..Default::default()
+        }])
+        .unwrap();
+
+        let live = InitialPattern {
+            name: "ヌル＋マスカレーナ".to_string(),
+            first: StringOrList::String("溟界の滓－ヌル".to_string()),
+            second: None,
+            third: None,
+            level: 1,
+            extra: Some(StringOrList::String("I:Pマスカレーナ".to_string())),
+        };
+        let dead = InitialPattern {
+            name: "ヌル＋アクセスコード".to_string(),
+            extra: Some(StringOrList::List(vec![
+                "I:Pマスカレーナ".to_string(),
+                "アクセスコード・トーカー".to_string(),
+            ])),
+            ..live.clone()
+        };
+
+        assert!(live.is_live(&deck));
+        assert!(!dead.is_live(&deck));
+
+        let issues = extra_issues(&deck, &[live, dead]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].index, 1);
+        assert_eq!(
+            issues[0].message,
+            "not in extra deck: アクセスコード・トーカー."
+        );
+    }
+}
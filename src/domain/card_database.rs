@@ -0,0 +1,404 @@
+use super::{is_query, Card, CardEffect, CardMeta, Deck, EffectAction, StringOrList};
+use crate::AppError;
+
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// カードデータベースの一枚のカード．`id`はカードに記載されたパスワード
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct CardEntry {
+    pub id: u32,
+    /// 日本語のカード名．デッキのカード名はこの名前に揃える．
+    pub name_ja: String,
+    /// 英語のカード名
+    pub name_en: Option<String>,
+    #[serde(flatten)]
+    pub meta: CardMeta,
+}
+
+/// CSVのカードデータベースの一行．`tags`は`;`で区切る．
+/// 列: `id,name_ja,name_en,type,attribute,race,level,tags`
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct CardRow {
+    pub id: u32,
+    pub name_ja: String,
+    pub name_en: Option<String>,
+    #[serde(rename = "type")]
+    pub card_type: Option<String>,
+    pub attribute: Option<String>,
+    pub race: Option<String>,
+    pub level: Option<u8>,
+    pub tags: Option<String>,
+}
+
+impl From<CardRow> for CardEntry {
+    fn from(row: CardRow) -> Self {
+        let tags = row
+            .tags
+            .iter()
+            .flat_map(|tags| tags.split(';'))
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Self {
+            id: row.id,
+            name_ja: row.name_ja,
+            name_en: row.name_en,
+            meta: CardMeta {
+                card_type: row.card_type,
+                attribute: row.attribute,
+                race: row.race,
+                level: row.level,
+                tags,
+            },
+        }
+    }
+}
+
+/// パスワードと日本語・英語のカード名で引けるカードデータベース．
+/// デッキのカード名をパスワードやどちらの言語の名前で書いても，日本語の名前とカードの情報を補完できる．
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardDatabase {
+    entries: Vec<CardEntry>,
+    /// パスワードから`entries`のインデックス
+    by_id: HashMap<u32, usize>,
+    /// 日本語・英語の名前から`entries`のインデックス．同じ名前のカードが複数ある場合は全て含む．
+    by_name: HashMap<String, Vec<usize>>,
+}
+
+impl CardDatabase {
+    /// パスワードが重複している場合はエラー
+    pub fn new(entries: Vec<CardEntry>) -> Result<Self, AppError> {
+        let mut by_id = HashMap::new();
+        let mut by_name: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for (i, entry) in entries.iter().enumerate() {
+            if by_id.insert(entry.id, i).is_some() {
+                return Err(AppError::InvalidDataError(format!(
+                    "Duplicate card id in card database: {}",
+                    entry.id
+                )));
+            }
+
+            let names = Some(&entry.name_ja).into_iter().chain(entry.name_en.iter());
+            for name in names {
+                let indices = by_name.entry(name.to_string()).or_default();
+                if !indices.contains(&i) {
+                    indices.push(i);
+                }
+            }
+        }
+
+        Ok(Self {
+            entries,
+            by_id,
+            by_name,
+        })
+    }
+
+    pub fn entries(&self) -> &[CardEntry] {
+        &self.entries
+    }
+
+    /// パスワードでカードを引く．
+    pub fn get(&self, id: u32) -> Result<&CardEntry, AppError> {
+        self.by_id
+            .get(&id)
+            .map(|i| &self.entries[*i])
+            .ok_or_else(|| AppError::CardNotFoundError(id.to_string()))
+    }
+
+    /// 日本語または英語の名前でカードを引く．別のカードが同じ名前を持つ場合はエラー．
+    /// 同じ日本語の名前のカード(イラスト違い)は最初のものを返す．
+    pub fn find(&self, name: &str) -> Result<&CardEntry, AppError> {
+        let indices = self
+            .by_name
+            .get(name)
+            .ok_or_else(|| AppError::CardNotFoundError(name.to_string()))?;
+        let first = &self.entries[indices[0]];
+
+        if indices
+            .iter()
+            .any(|i| self.entries[*i].name_ja != first.name_ja)
+        {
+            return Err(AppError::AmbiguousCardError {
+                name: name.to_string(),
+                ids: indices.iter().map(|i| self.entries[*i].id).collect(),
+            });
+        }
+        Ok(first)
+    }
+
+    /// 数字のみの場合はパスワード，それ以外は名前としてカードを引く．
+    pub fn lookup(&self, key: &str) -> Result<&CardEntry, AppError> {
+        let key = key.trim();
+        match key.parse() {
+            Ok(id) if key.bytes().all(|b| b.is_ascii_digit()) => self.get(id),
+            _ => self.find(key),
+        }
+    }
+
+    /// カード名の一覧の各カード名を日本語の名前に揃える．クエリはそのまま残す．
+    fn enrich_names(&self, names: &StringOrList) -> Result<StringOrList, AppError> {
+        let enrich_name = |name: &String| -> Result<String, AppError> {
+            match is_query(name) {
+                true => Ok(name.to_string()),
+                false => Ok(self.lookup(name)?.name_ja.to_string()),
+            }
+        };
+
+        Ok(match names {
+            StringOrList::String(name) => StringOrList::String(enrich_name(name)?),
+            StringOrList::List(list) => {
+                StringOrList::List(list.iter().map(enrich_name).collect::<Result<_, _>>()?)
+            }
+        })
+    }
+
+    /// 効果のサーチ先とめくった中から加えるカードの名前を日本語の名前に揃える．
+    fn enrich_effect(&self, effect: &CardEffect) -> Result<CardEffect, AppError> {
+        let enrich_actions = |actions: &[EffectAction]| {
+            actions
+                .iter()
+                .map(|action| {
+                    Ok(match action {
+                        EffectAction::Search { targets } => EffectAction::Search {
+                            targets: self.enrich_names(targets)?,
+                        },
+                        EffectAction::Excavate { number, targets } => EffectAction::Excavate {
+                            number: *number,
+                            targets: self.enrich_names(targets)?,
+                        },
+                        _ => action.clone(),
+                    })
+                })
+                .collect::<Result<Vec<_>, AppError>>()
+        };
+
+        Ok(CardEffect {
+            cost: enrich_actions(&effect.cost)?,
+            actions: enrich_actions(&effect.actions)?,
+            ..effect.clone()
+        })
+    }
+
+    /// カード名を日本語の名前に揃え，省略されたカードの情報をデータベースの値で補う．タグは両方を合わせる．
+    /// 代用カードのサーチ先(`acts_as`)と効果の対象のカード名も日本語の名前に揃え，データベースに無い場合はエラー
+    pub fn enrich(&self, card: &Card) -> Result<Card, AppError> {
+        let entry = self.lookup(&card.name)?;
+        let meta = &card.meta;
+
+        let mut tags = entry.meta.tags.clone();
+        for tag in meta.tags.iter() {
+            if !tags.contains(tag) {
+                tags.push(tag.to_string());
+            }
+        }
+
+        Ok(Card {
+            name: entry.name_ja.to_string(),
+            effects: card
+                .effects
+                .iter()
+                .map(|effect| self.enrich_effect(effect))
+                .collect::<Result<_, _>>()?,
+            acts_as: card
+                .acts_as
+                .as_ref()
+                .map(|acts_as| self.enrich_names(acts_as))
+                .transpose()?,
+            meta: CardMeta {
+                card_type: meta.card_type.clone().or(entry.meta.card_type.clone()),
+                attribute: meta.attribute.clone().or(entry.meta.attribute.clone()),
+                race: meta.race.clone().or(entry.meta.race.clone()),
+                level: meta.level.or(entry.meta.level),
+                tags,
+            },
+            ..card.clone()
+        })
+    }
+
+    fn enrich_cards(&self, cards: &[Card]) -> Result<Vec<Card>, AppError> {
+        cards.iter().map(|card| self.enrich(card)).collect()
+    }
+
+    /// メイン・エクストラ・サイドデッキの全てのカードを補完したデッキ．
+    /// パスワードと名前で書いた同じカードは一つにまとめる．
+    pub fn enrich_deck(&self, deck: &Deck) -> Result<Deck, AppError> {
+        Deck::new(self.enrich_cards(deck)?)?
+            .with_extra(self.enrich_cards(deck.extra())?)?
+            .with_side(self.enrich_cards(deck.side())?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CardDatabase, CardEntry, CardRow};
+    use crate::domain::{Card, CardEffect, CardMeta, Deck, EffectAction, StringOrList};
+    use crate::AppError;
+
+    fn database() -> CardDatabase {
+        let entry = |id: u32, name_ja: &str, name_en: &str| CardEntry {
+            id,
+            name_ja: name_ja.to_string(),
+            name_en: Some(name_en.to_string()),
+            meta: CardMeta {
+                card_type: Some("モンスター".to_string()),
+                race: Some("爬虫類".to_string()),
+                level: Some(2),
+                tags: vec!["初動".to_string()],
+                ..Default::default()
+            },
+        };
+
+        CardDatabase::new(vec![
+            entry(1, "溟界の滓－ヌル", "Ogdoadic Remnant Nunu"),
+            entry(2, "溟界の滓－ナイア", "Ogdoadic Remnant Naunet"),
+            // イラスト違い
+            entry(3, "溟界の滓－ナイア", "Ogdoadic Remnant Naunet"),
+            entry(4, "カードA", "Card"),
+            entry(5, "カードB", "Card"),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_lookup() {
+        let db = database();
+
+        assert_eq!(db.lookup("1").unwrap().name_ja, "溟界の滓－ヌル");
+        assert_eq!(db.lookup("Ogdoadic Remnant Nunu").unwrap().id, 1);
+        assert_eq!(db.lookup("溟界の滓－ナイア").unwrap().id, 2);
+        assert!(matches!(
+            db.lookup("99"),
+            Err(AppError::CardNotFoundError(_))
+        ));
+        assert!(matches!(
+            db.lookup("存在しないカード"),
+            Err(AppError::CardNotFoundError(_))
+        ));
+        match db.lookup("Card") {
+            Err(AppError::AmbiguousCardError { ids, .. }) => assert_eq!(ids, vec![4, 5]),
+            result => panic!("unexpected result: {result:?}"),
+        }
+
+        let duplicate = vec![db.entries()[0].clone(); 2];
+        assert!(CardDatabase::new(duplicate).is_err());
+    }
+
+    #[test]
+    fn test_enrich_deck() {
+        let db = database();
+        let card = |name: &str, number: u8| Card {
+            name: name.to_string(),
+            number,
+            ..Default::default()
+        };
+
+        let deck = Deck::new(vec![
+            card("1", 2),
+            Card {
+                meta: CardMeta {
+                    level: Some(3),
+                    tags: vec!["手札誘発".to_string()],
+                    ..Default::default()
+                },
+                ..card("Ogdoadic Remnant Naunet", 3)
+            },
+        ])
+        .unwrap()
+        .with_side(vec![card("溟界の滓－ヌル", 1)])
+        .unwrap();
+
+        let enriched = db.enrich_deck(&deck).unwrap();
+        assert_eq!(enriched.number_of("溟界の滓－ヌル"), 2);
+        assert_eq!(enriched.side()[0].name, "溟界の滓－ヌル");
+
+        let naunet = &enriched[enriched.index_of("溟界の滓－ナイア").unwrap()];
+        assert_eq!(naunet.meta.race.as_deref(), Some("爬虫類"));
+        // デッキに書いた値を優先する
+        assert_eq!(naunet.meta.level, Some(3));
+        assert_eq!(naunet.meta.tags, vec!["初動", "手札誘発"]);
+
+        // パスワードと名前で書いた同じカードはまとめる
+        let merged = Deck::new(vec![card("1", 1), card("溟界の滓－ヌル", 2)]).unwrap();
+        assert_eq!(db.enrich_deck(&merged).unwrap().len(), 1);
+
+        let missing = Deck::new(vec![card("存在しないカード", 1)]).unwrap();
+        assert!(db.enrich_deck(&missing).is_err());
+    }
+
+    #[test]
+    fn test_enrich_references() {
+        let db = database();
+        let searcher = Card {
+            effects: vec![CardEffect {
+                cost: Vec::new(),
+                actions: vec![EffectAction::Search {
+                    targets: StringOrList::List(vec!["1".to_string(), "@race=爬虫類".to_string()]),
+                }],
+                once_per_turn: false,
+                remains: false,
+            }],
+            acts_as: Some(StringOrList::String("Ogdoadic Remnant Naunet".to_string())),
+            ..db.enrich(&Card {
+                name: "4".to_string(),
+                number: 1,
+                ..Default::default()
+            })
+            .unwrap()
+        };
+
+        let enriched = db.enrich(&searcher).unwrap();
+        assert_eq!(
+            enriched.acts_as,
+            Some(StringOrList::String("溟界の滓－ナイア".to_string()))
+        );
+        // クエリはそのまま残す
+        assert_eq!(
+            enriched.effects[0].actions[0],
+            EffectAction::Search {
+                targets: StringOrList::List(vec![
+                    "溟界の滓－ヌル".to_string(),
+                    "@race=爬虫類".to_string()
+                ]),
+            }
+        );
+
+        let missing = Card {
+            acts_as: Some(StringOrList::String("存在しないカード".to_string())),
+            ..searcher.clone()
+        };
+        assert!(matches!(
+            db.enrich(&missing),
+            Err(AppError::CardNotFoundError(_))
+        ));
+        let ambiguous = Card {
+            acts_as: Some(StringOrList::String("Card".to_string())),
+            ..searcher
+        };
+        assert!(matches!(
+            db.enrich(&ambiguous),
+            Err(AppError::AmbiguousCardError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_card_row() {
+        let row = CardRow {
+            id: 1,
+            name_ja: "溟界の滓－ヌル".to_string(),
+            name_en: None,
+            card_type: None,
+            attribute: Some("闇".to_string()),
+            race: None,
+            level: Some(2),
+            tags: Some("初動; 展開".to_string()),
+        };
+        let entry = CardEntry::from(row);
+        assert_eq!(entry.meta.tags, vec!["初動", "展開"]);
+        assert_eq!(entry.meta.attribute.as_deref(), Some("闇"));
+    }
+}
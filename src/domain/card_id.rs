@@ -0,0 +1,19 @@
+/// デッキ内のカードの種類を表す整数のID．`Deck`のカードのインデックスと一致する．
+/// 全探索やサンプリングの内部ではカード名の代わりにIDで比較し，カード名は出力の際にのみ用いる．
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct CardId(pub u16);
+
+impl CardId {
+    /// `Deck`のカードやIDごとの枚数の配列のインデックス
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// IDの羅列で表した手札を，IDごとの枚数の配列`counts`に変換する．
+pub fn fill_counts(counts: &mut [u8], ids: &[CardId]) {
+    counts.fill(0);
+    for id in ids.iter() {
+        counts[id.index()] += 1;
+    }
+}
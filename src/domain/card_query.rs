@@ -0,0 +1,206 @@
+use super::Card;
+use crate::AppError;
+
+use std::str::FromStr;
+
+/// パターンのスロットやグループのカード名のうち，クエリとして扱う要素の接頭辞
+pub const QUERY_PREFIX: char = '@';
+
+/// カード名の代わりに`@`で始まるクエリが指定されているかどうか
+pub fn is_query(name: &str) -> bool {
+    name.starts_with(QUERY_PREFIX)
+}
+
+/// クエリの比較演算子
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    /// 長い演算子から順に並べたもの．`<=`を`<`より先に判定する．
+    const OPERATORS: [(&'static str, Self); 6] = [
+        ("<=", Self::Le),
+        (">=", Self::Ge),
+        ("!=", Self::Ne),
+        ("=", Self::Eq),
+        ("<", Self::Lt),
+        (">", Self::Gt),
+    ];
+
+    fn compare<T: Ord>(&self, x: T, y: T) -> bool {
+        match self {
+            Self::Eq => x == y,
+            Self::Ne => x != y,
+            Self::Lt => x < y,
+            Self::Le => x <= y,
+            Self::Gt => x > y,
+            Self::Ge => x >= y,
+        }
+    }
+}
+
+/// クエリの一つの条件．カードにその情報が無い場合は`!=`を含めてどの条件にも一致しない．
+/// タグは持っていないことも情報として扱い，持たないタグとは`!=`で一致する．
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Condition {
+    /// カードの種類(`type`)
+    CardType(Comparison, String),
+    /// 属性(`attribute`)
+    Attribute(Comparison, String),
+    /// 種族(`race`)
+    Race(Comparison, String),
+    /// レベルまたはランク(`level`)
+    Level(Comparison, u8),
+    /// タグ(`tag`)．`=`はタグを持つ，`!=`はタグを持たない
+    Tag(Comparison, String),
+}
+
+impl Condition {
+    pub fn matches(&self, card: &Card) -> bool {
+        let text = |comparison: &Comparison, field: &Option<String>, value: &String| {
+            field
+                .as_ref()
+                .is_some_and(|field| comparison.compare(field, value))
+        };
+
+        match self {
+            Self::CardType(comparison, value) => text(comparison, &card.meta.card_type, value),
+            Self::Attribute(comparison, value) => text(comparison, &card.meta.attribute, value),
+            Self::Race(comparison, value) => text(comparison, &card.meta.race, value),
+            Self::Level(comparison, value) => card
+                .meta
+                .level
+                .is_some_and(|level| comparison.compare(level, *value)),
+            Self::Tag(comparison, value) => {
+                comparison.compare(card.meta.tags.contains(value), true)
+            }
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, comparison, value) = Comparison::OPERATORS
+            .iter()
+            .filter_map(|(op, comparison)| {
+                let (key, value) = s.split_once(op)?;
+                Some((key, *comparison, value))
+            })
+            // 最も左にある演算子を用いる
+            .min_by_key(|(key, _, _)| key.len())
+            .ok_or_else(|| format!("no operator in `{s}`"))?;
+        let (key, value) = (key.trim(), value.trim().to_string());
+
+        let equality = |condition: fn(Comparison, String) -> Self| match comparison {
+            Comparison::Eq | Comparison::Ne => Ok(condition(comparison, value.clone())),
+            _ => Err(format!("`{key}` supports only = and !=")),
+        };
+
+        match key {
+            "type" => equality(Self::CardType),
+            "attribute" => equality(Self::Attribute),
+            "race" => equality(Self::Race),
+            "tag" => equality(Self::Tag),
+            "level" => value
+                .parse()
+                .map(|level| Self::Level(comparison, level))
+                .map_err(|_| format!("invalid level `{value}`")),
+            _ => Err(format!("unknown key `{key}`")),
+        }
+    }
+}
+
+/// `@`で始まるカードのクエリ．`&`で区切った条件を全て満たすカードを表す．
+/// 例: `@race=爬虫類`，`@level<=4`，`@type=モンスター&attribute=闇`，`@tag=手札誘発`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CardQuery {
+    pub conditions: Vec<Condition>,
+}
+
+impl CardQuery {
+    pub fn matches(&self, card: &Card) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.matches(card))
+    }
+}
+
+impl FromStr for CardQuery {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            |message: String| AppError::InvalidDataError(format!("Invalid query {s}: {message}"));
+
+        let body = s
+            .strip_prefix(QUERY_PREFIX)
+            .ok_or_else(|| invalid(format!("query must start with {QUERY_PREFIX}")))?;
+        let conditions = body
+            .split('&')
+            .map(|condition| condition.parse().map_err(invalid))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { conditions })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CardQuery, Comparison, Condition};
+    use crate::domain::{Card, CardMeta};
+
+    #[test]
+    fn test_card_query() {
+        let query = "@race=爬虫類&level<=4".parse::<CardQuery>().unwrap();
+        assert_eq!(
+            query.conditions,
+            vec![
+                Condition::Race(Comparison::Eq, "爬虫類".to_string()),
+                Condition::Level(Comparison::Le, 4),
+            ]
+        );
+
+        let card = |race: &str, level: u8| Card {
+            name: "カード".to_string(),
+            number: 1,
+            meta: CardMeta {
+                race: Some(race.to_string()),
+                level: Some(level),
+                tags: vec!["初動".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(query.matches(&card("爬虫類", 4)));
+        assert!(!query.matches(&card("爬虫類", 5)));
+        assert!(!query.matches(&card("魔法使い族", 1)));
+        // 情報が無いカードは一致しない
+        assert!(!query.matches(&Card::default()));
+        // `!=`でも情報が無いカードは一致しない
+        let not_race = "@race!=爬虫類".parse::<CardQuery>().unwrap();
+        assert!(not_race.matches(&card("魔法使い族", 1)));
+        assert!(!not_race.matches(&card("爬虫類", 1)));
+        assert!(!not_race.matches(&Card::default()));
+        let not_level = "@level!=4".parse::<CardQuery>().unwrap();
+        assert!(not_level.matches(&card("爬虫類", 1)));
+        assert!(!not_level.matches(&Card::default()));
+
+        let tag = "@tag=初動".parse::<CardQuery>().unwrap();
+        assert!(tag.matches(&card("爬虫類", 4)));
+        let not_tag = "@tag!=初動".parse::<CardQuery>().unwrap();
+        assert!(!not_tag.matches(&card("爬虫類", 4)));
+
+        assert!("@race<爬虫類".parse::<CardQuery>().is_err());
+        assert!("@level=★4".parse::<CardQuery>().is_err());
+        assert!("@color=黒".parse::<CardQuery>().is_err());
+        assert!("race=爬虫類".parse::<CardQuery>().is_err());
+    }
+}
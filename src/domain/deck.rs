@@ -0,0 +1,433 @@
+use super::{is_query, Card, CardId, CardQuery, StringOrList};
+use crate::AppError;
+
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+
+/// デッキのjsonの形式．メインデッキのみの配列か，メインデッキ・エクストラデッキ・サイドデッキに分けたオブジェクト
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(untagged)]
+pub enum DeckFile {
+    Main(Vec<Card>),
+    Sections(DeckSections),
+}
+
+/// メインデッキ・エクストラデッキ・サイドデッキに分けたデッキ
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct DeckSections {
+    pub main: Vec<Card>,
+    #[serde(default)]
+    pub extra: Vec<Card>,
+    #[serde(default)]
+    pub side: Vec<Card>,
+}
+
+/// メインデッキの最小枚数
+pub const MAIN_DECK_MIN: usize = 40;
+/// メインデッキの最大枚数
+pub const MAIN_DECK_MAX: usize = 60;
+/// エクストラデッキの最大枚数
+pub const EXTRA_DECK_MAX: usize = 15;
+/// サイドデッキの最大枚数
+pub const SIDE_DECK_MAX: usize = 15;
+
+/// 検証済みのデッキ．同じ名前のカードは一つにまとめ，最初に現れた順番に並べる．
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+#[serde(try_from = "DeckFile")]
+pub struct Deck {
+    /// メインデッキのカード
+    cards: Vec<Card>,
+    /// カード名から`cards`のインデックス
+    indices: HashMap<String, usize>,
+    /// デッキの枚数
+    size: usize,
+    /// エクストラデッキのカード．引くことは無く，パターンの条件の判定にのみ用いる．
+    extra: Vec<Card>,
+    /// サイドデッキのカード．サイドチェンジの計画でメインデッキと入れ替える．
+    side: Vec<Card>,
+}
+
+/// 同じ名前のカードをまとめ，カード名とインデックスの対応と合わせて返す．
+/// カード名が空の場合や，同じ名前のカードで効果やサーチ先が異なる場合はエラー
+fn merge_cards(cards: Vec<Card>) -> Result<(Vec<Card>, HashMap<String, usize>), AppError> {
+    let mut merged: Vec<Card> = Vec::new();
+    let mut indices: HashMap<String, usize> = HashMap::new();
+
+    for card in cards.into_iter() {
+        if card.name.is_empty() {
+            return Err(AppError::InvalidDataError(
+                "Card name is empty.".to_string(),
+            ));
+        }
+
+        match indices.get(&card.name) {
+            Some(i) => {
+                let merged_card = &mut merged[*i];
+                if merged_card.effects != card.effects
+                    || merged_card.acts_as != card.acts_as
+                    || merged_card.meta != card.meta
+                {
+                    return Err(AppError::InvalidDataError(format!(
+                        "Conflicting definitions of card: {}",
+                        card.name
+                    )));
+                }
+                merged_card.number =
+                    merged_card.number.checked_add(card.number).ok_or_else(|| {
+                        AppError::InvalidDataError(format!("Too many cards: {}", card.name))
+                    })?;
+            }
+            None => {
+                indices.insert(card.name.to_string(), merged.len());
+                merged.push(card);
+            }
+        }
+    }
+
+    Ok((merged, indices))
+}
+
+impl Deck {
+    /// メインデッキのみのデッキ．カード名が空の場合や，同じ名前のカードで効果やサーチ先が異なる場合はエラー
+    pub fn new(cards: Vec<Card>) -> Result<Self, AppError> {
+        let (cards, indices) = merge_cards(cards)?;
+
+        if cards.len() > u16::MAX as usize + 1 {
+            return Err(AppError::InvalidDataError(
+                "Too many kinds of cards.".to_string(),
+            ));
+        }
+
+        let size = cards.iter().map(|card| card.number as usize).sum();
+
+        Ok(Self {
+            cards,
+            indices,
+            size,
+            extra: Vec::new(),
+            side: Vec::new(),
+        })
+    }
+
+    /// エクストラデッキを設定する．メインデッキと同様に検証する．
+    pub fn with_extra(self, extra: Vec<Card>) -> Result<Self, AppError> {
+        let (extra, _) = merge_cards(extra)?;
+        Ok(Self { extra, ..self })
+    }
+
+    /// サイドデッキを設定する．メインデッキと同様に検証する．
+    pub fn with_side(self, side: Vec<Card>) -> Result<Self, AppError> {
+        let (side, _) = merge_cards(side)?;
+        Ok(Self { side, ..self })
+    }
+
+    /// エクストラデッキとサイドデッキはそのままで，メインデッキを`cards`に置き換えたデッキ
+    pub fn with_cards(&self, cards: Vec<Card>) -> Result<Self, AppError> {
+        Ok(Self {
+            extra: self.extra.clone(),
+            side: self.side.clone(),
+            ..Self::new(cards)?
+        })
+    }
+
+    /// 大会で使用できる枚数かどうかを検証する．メインデッキは40枚から60枚，
+    /// エクストラデッキとサイドデッキはそれぞれ15枚まで
+    pub fn validate_size(&self) -> Result<(), AppError> {
+        for (section, size, min, max) in [
+            ("main", self.size, MAIN_DECK_MIN, MAIN_DECK_MAX),
+            ("extra", self.extra_size(), 0, EXTRA_DECK_MAX),
+            ("side", self.side_size(), 0, SIDE_DECK_MAX),
+        ] {
+            if !(min..=max).contains(&size) {
+                return Err(AppError::DeckSizeError {
+                    section: section.to_string(),
+                    size,
+                    min,
+                    max,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// デッキの枚数
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
+
+    /// カード名に対応した`cards`のインデックス
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.indices.get(name).copied()
+    }
+
+    /// カード名ごとの`cards`のインデックス
+    pub fn indices(&self) -> &HashMap<String, usize> {
+        &self.indices
+    }
+
+    /// カードの枚数．デッキに無い場合は0
+    pub fn number_of(&self, name: &str) -> u8 {
+        self.index_of(name).map_or(0, |i| self.cards[i].number)
+    }
+
+    /// エクストラデッキのカード
+    pub fn extra(&self) -> &[Card] {
+        &self.extra
+    }
+
+    /// エクストラデッキでのカードの枚数．登録されていない場合は0
+    pub fn extra_number_of(&self, name: &str) -> u8 {
+        self.extra
+            .iter()
+            .find(|card| card.name == name)
+            .map_or(0, |card| card.number)
+    }
+
+    /// エクストラデッキの枚数
+    pub fn extra_size(&self) -> usize {
+        self.extra.iter().map(|card| card.number as usize).sum()
+    }
+
+    /// サイドデッキのカード
+    pub fn side(&self) -> &[Card] {
+        &self.side
+    }
+
+    /// サイドデッキの枚数
+    pub fn side_size(&self) -> usize {
+        self.side.iter().map(|card| card.number as usize).sum()
+    }
+
+    /// エクストラデッキに登録されているかどうか
+    pub fn in_extra(&self, name: &str) -> bool {
+        self.extra
+            .iter()
+            .any(|card| card.name == name && card.number > 0)
+    }
+
+    /// `names`のいずれかのカードの枚数の合計
+    pub fn number_in(&self, names: &HashSet<&String>) -> u64 {
+        names.iter().map(|name| self.number_of(name) as u64).sum()
+    }
+
+    /// カード名に対応したID
+    pub fn id_of(&self, name: &str) -> Option<CardId> {
+        self.index_of(name).map(|i| CardId(i as u16))
+    }
+
+    /// IDに対応したカード名
+    pub fn name_of(&self, id: CardId) -> &str {
+        &self.cards[id.index()].name
+    }
+
+    /// `names`のうちデッキに含まれるカードの名前を重複無く取得する．`@`で始まる要素はクエリとして，
+    /// 条件を満たすデッキのカード全てにデッキの順番で展開する．不正なクエリがある場合はエラー
+    pub fn resolve(&self, names: &StringOrList) -> Result<Vec<&String>, AppError> {
+        let mut resolved: Vec<&String> = Vec::new();
+        let mut push = |name: &'_ String| {
+            if let Some(i) = self.index_of(name) {
+                let name = &self.cards[i].name;
+                if !resolved.contains(&name) {
+                    resolved.push(name);
+                }
+            }
+        };
+
+        for name in names.as_vec() {
+            if !is_query(name) {
+                push(name);
+                continue;
+            }
+            let query = name.parse::<CardQuery>()?;
+            for card in self.cards.iter().filter(|card| query.matches(card)) {
+                push(&card.name);
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// `names`のカードのID．デッキに無いカードは除き，クエリは展開する．不正なクエリがある場合はエラー
+    pub fn ids_of(&self, names: &StringOrList) -> Result<Vec<CardId>, AppError> {
+        Ok(self
+            .resolve(names)?
+            .into_iter()
+            .filter_map(|name| self.id_of(name))
+            .collect())
+    }
+
+    /// 枚数分並べたデッキのカードのID．IDの順にソートされている．
+    pub fn card_ids(&self) -> Vec<CardId> {
+        self.cards
+            .iter()
+            .enumerate()
+            .flat_map(|(i, card)| std::iter::repeat_n(CardId(i as u16), card.number as usize))
+            .collect()
+    }
+
+    /// IDごとのデッキ内の枚数
+    pub fn numbers(&self) -> Vec<u8> {
+        self.cards.iter().map(|card| card.number).collect()
+    }
+}
+
+impl TryFrom<Vec<Card>> for Deck {
+    type Error = AppError;
+
+    fn try_from(cards: Vec<Card>) -> Result<Self, Self::Error> {
+        Self::new(cards)
+    }
+}
+
+impl TryFrom<DeckSections> for Deck {
+    type Error = AppError;
+
+    fn try_from(sections: DeckSections) -> Result<Self, Self::Error> {
+        Self::new(sections.main)?
+            .with_extra(sections.extra)?
+            .with_side(sections.side)
+    }
+}
+
+impl TryFrom<DeckFile> for Deck {
+    type Error = AppError;
+
+    fn try_from(file: DeckFile) -> Result<Self, Self::Error> {
+        match file {
+            DeckFile::Main(cards) => Self::new(cards),
+            DeckFile::Sections(sections) => Self::try_from(sections),
+        }
+    }
+}
+
+impl Deref for Deck {
+    type Target = [Card];
+
+    fn deref(&self) -> &Self::Target {
+        &self.cards
+    }
+}
+
+impl<'a> IntoIterator for &'a Deck {
+    type Item = &'a Card;
+    type IntoIter = std::slice::Iter<'a, Card>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cards.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Deck;
+    use crate::domain::{Card, CardId, StringOrList};
+    use crate::AppError;
+
+    #[test]
+    fn test_deck() {
+        let deck = Deck::new(vec![
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 2,
+                ..Default::default()
+            },
+            Card {
+                name: "灰流うらら".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 1,
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+
+        assert_eq!(deck.len(), 2);
+        assert_eq!(deck.size(), 6);
+        assert_eq!(deck.number_of("溟界の滓－ヌル"), 3);
+        assert_eq!(deck.number_of("増殖するG"), 0);
+        assert_eq!(deck.index_of("灰流うらら"), Some(1));
+        assert_eq!(deck.name_of(CardId(1)), "灰流うらら");
+        assert_eq!(
+            deck.card_ids(),
+            vec![
+                CardId(0),
+                CardId(0),
+                CardId(0),
+                CardId(1),
+                CardId(1),
+                CardId(1)
+            ]
+        );
+
+        let json = r#"[
+            {"name": "溟界の滓－ヌル", "number": 3, "type": "モンスター", "race": "爬虫類", "level": 4},
+            {"name": "溟界神－オグドアビス", "number": 1, "type": "モンスター", "race": "爬虫類", "level": 10},
+            {"name": "灰流うらら", "number": 3, "type": "モンスター", "race": "アンデット族", "tags": ["手札誘発"], "rarity": "SE"}
+        ]"#;
+        let deck = serde_json::from_str::<Deck>(json).unwrap();
+        let resolved = |names: Vec<&str>| {
+            deck.resolve(&StringOrList::List(
+                names.into_iter().map(str::to_string).collect(),
+            ))
+            .unwrap()
+        };
+        assert_eq!(
+            resolved(vec![
+                "灰流うらら",
+                "@race=爬虫類&level<=4",
+                "存在しないカード"
+            ]),
+            vec!["灰流うらら", "溟界の滓－ヌル"]
+        );
+        assert_eq!(
+            resolved(vec!["@race=爬虫類", "溟界の滓－ヌル"]),
+            vec!["溟界の滓－ヌル", "溟界神－オグドアビス"]
+        );
+        assert_eq!(resolved(vec!["@tag=手札誘発"]), vec!["灰流うらら"]);
+        // 不正なクエリは無視せずエラー
+        assert!(deck
+            .resolve(&StringOrList::String("@color=黒".to_string()))
+            .is_err());
+
+        let json = r#"[{"name": "灰流うらら", "number": 3}]"#;
+        let deck = serde_json::from_str::<Deck>(json).unwrap();
+        assert_eq!(deck.size(), 3);
+
+        let conflicting = vec![
+            Card {
+                name: "溟界の蛇睡蓮".to_string(),
+                number: 1,
+                acts_as: Some(StringOrList::String("溟界の滓－ヌル".to_string())),
+                ..Default::default()
+            },
+            Card {
+                name: "溟界の蛇睡蓮".to_string(),
+                number: 1,
+                ..Default::default()
+            },
+        ];
+        assert!(Deck::new(conflicting).is_err());
+
+        let json = r#"{
+            "main": [{"name": "灰流うらら", "number": 3}],
+            "extra": [{"name": "I:Pマスカレーナ", "number": 1}]
+        }"#;
+        let deck = serde_json::from_str::<Deck>(json).unwrap();
+        assert_eq!(deck.size(), 3);
+        assert_eq!(deck.extra_size(), 1);
+        assert!(deck.in_extra("I:Pマスカレーナ"));
+        assert!(!deck.in_extra("灰流うらら"));
+        assert!(matches!(
+            deck.validate_size(),
+            Err(AppError::DeckSizeError { size: 3, .. })
+        ));
+        assert!(Deck::new(vec![Card::default()]).is_err());
+    }
+}
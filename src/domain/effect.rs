@@ -0,0 +1,89 @@
+use super::StringOrList;
+
+use serde::Deserialize;
+
+/// カードの効果．コストを支払った後に効果処理を順に行う．
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct CardEffect {
+    /// 発動コスト．全て支払えない場合は発動できない．
+    #[serde(default)]
+    pub cost: Vec<EffectAction>,
+    /// 効果処理
+    pub actions: Vec<EffectAction>,
+    /// 同名カードの効果は1ターンに1度しか発動できないかどうか
+    #[serde(default)]
+    pub once_per_turn: bool,
+    /// 発動後も手札に残り，パターンの判定に含めるかどうか
+    #[serde(default)]
+    pub remains: bool,
+}
+
+/// 効果処理・コストの一つ一つの動作
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Hash)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EffectAction {
+    /// デッキから`number`枚ドローする
+    Draw { number: u8 },
+    /// デッキの上から`number`枚除外する
+    Banish { number: u8 },
+    /// デッキから`targets`のうち一枚を手札に加える
+    Search { targets: StringOrList },
+    /// デッキの上から`number`枚めくり，その中の`targets`のうち一枚を手札に加える．残りはデッキの下に戻す
+    Excavate { number: u8, targets: StringOrList },
+}
+
+impl EffectAction {
+    /// この動作に必要なデッキの枚数
+    pub fn required_deck_n(&self) -> usize {
+        match self {
+            Self::Draw { number } | Self::Banish { number } | Self::Excavate { number, .. } => {
+                *number as usize
+            }
+            Self::Search { .. } => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CardEffect, EffectAction, StringOrList};
+
+    #[test]
+    fn test_deserialize() {
+        let json_str = r#"
+        [
+            {
+                "cost": [{"type": "banish", "number": 10}],
+                "actions": [{"type": "draw", "number": 2}],
+                "once_per_turn": true
+            },
+            {
+                "actions": [{"type": "search", "targets": ["溟界の滓－ヌル", "溟界の滓－ナイア"]}]
+            }
+        ]
+        "#;
+
+        assert_eq!(
+            serde_json::from_str::<Vec<CardEffect>>(json_str).unwrap(),
+            vec![
+                CardEffect {
+                    cost: vec![EffectAction::Banish { number: 10 }],
+                    actions: vec![EffectAction::Draw { number: 2 }],
+                    once_per_turn: true,
+                    remains: false,
+                },
+                CardEffect {
+                    cost: Vec::new(),
+                    actions: vec![EffectAction::Search {
+                        targets: StringOrList::List(vec![
+                            "溟界の滓－ヌル".to_string(),
+                            "溟界の滓－ナイア".to_string()
+                        ])
+                    }],
+                    once_per_turn: false,
+                    remains: false,
+                }
+            ]
+        )
+    }
+}
@@ -0,0 +1,237 @@
+use super::{Card, Deck};
+use crate::AppError;
+
+use serde::Deserialize;
+
+/// サイドチェンジで入れ替えるカードとその枚数
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct SideCard {
+    pub name: String,
+    pub number: u8,
+    /// エクストラデッキのカードかどうか．trueの場合はメインデッキではなくエクストラデッキと入れ替える
+    #[serde(default)]
+    pub extra: bool,
+}
+
+/// 対戦相手ごとのサイドチェンジの計画．サイドデッキから`cards_in`を入れ，メインデッキから`cards_out`を抜く．
+/// メインデッキとエクストラデッキのそれぞれで，入れる枚数と抜く枚数は等しい必要がある．
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct SidingPlan {
+    /// 対戦相手(例: vs ティアラメンツ)
+    pub name: String,
+    #[serde(rename = "in", default)]
+    pub cards_in: Vec<SideCard>,
+    #[serde(rename = "out", default)]
+    pub cards_out: Vec<SideCard>,
+}
+
+/// `from`から`side_card`を取り除き，`to`に加える．`from`の枚数が足りない場合はエラー
+fn move_cards(
+    from: &mut Vec<Card>,
+    to: &mut Vec<Card>,
+    side_card: &SideCard,
+    from_section: &str,
+) -> Result<(), AppError> {
+    let Some(from_card) = from
+        .iter_mut()
+        .find(|card| card.name == side_card.name && card.number >= side_card.number)
+    else {
+        return Err(AppError::InvalidDataError(format!(
+            "Not enough {} in {from_section} deck: {}",
+            side_card.name, side_card.number
+        )));
+    };
+    from_card.number -= side_card.number;
+
+    match to.iter_mut().find(|card| card.name == side_card.name) {
+        Some(to_card) => {
+            to_card.number = to_card
+                .number
+                .checked_add(side_card.number)
+                .ok_or_else(|| {
+                    AppError::InvalidDataError(format!("Too many {}.", side_card.name))
+                })?;
+        }
+        None => to.push(Card {
+            number: side_card.number,
+            ..from_card.clone()
+        }),
+    }
+    from.retain(|card| card.number > 0);
+
+    Ok(())
+}
+
+/// `side_cards`のうち，エクストラデッキのカードかどうかが`extra`であるものの枚数の合計
+fn number_of(side_cards: &[SideCard], extra: bool) -> usize {
+    side_cards
+        .iter()
+        .filter(|side_card| side_card.extra == extra)
+        .map(|side_card| side_card.number as usize)
+        .sum()
+}
+
+impl SidingPlan {
+    /// 入れる枚数と抜く枚数がメインデッキとエクストラデッキのそれぞれで等しいかどうかを検証する．
+    pub fn validate(&self) -> Result<(), AppError> {
+        for (section, extra) in [("main", false), ("extra", true)] {
+            let in_n = number_of(&self.cards_in, extra);
+            let out_n = number_of(&self.cards_out, extra);
+            if in_n != out_n {
+                return Err(AppError::InvalidDataError(format!(
+                    "Plan {} puts {in_n} cards into {section} deck but takes {out_n} out.",
+                    self.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// サイドチェンジした後のデッキ．抜いたカードはサイドデッキに移す．
+    /// `extra`のカードはエクストラデッキと入れ替える．計画が`validate`を満たさない場合や，
+    /// 入れ替えるカードが足りない場合，入れ替えた後のデッキの枚数が大会で使用できない場合はエラー
+    pub fn apply(&self, deck: &Deck) -> Result<Deck, AppError> {
+        self.validate()?;
+
+        let mut main = deck.to_vec();
+        let mut extra = deck.extra().to_vec();
+        let mut side = deck.side().to_vec();
+
+        for side_card in self.cards_out.iter() {
+            match side_card.extra {
+                false => move_cards(&mut main, &mut side, side_card, "main")?,
+                true => move_cards(&mut extra, &mut side, side_card, "extra")?,
+            }
+        }
+        for side_card in self.cards_in.iter() {
+            match side_card.extra {
+                false => move_cards(&mut side, &mut main, side_card, "side")?,
+                true => move_cards(&mut side, &mut extra, side_card, "side")?,
+            }
+        }
+
+        let sided = Deck::new(main)?.with_extra(extra)?.with_side(side)?;
+        sided.validate_size()?;
+
+        Ok(sided)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SideCard, SidingPlan};
+    use crate::domain::{Card, Deck};
+
+    #[test]
+    fn test_siding_plan() {
+        let deck = Deck::new(vec![
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "増殖するG".to_string(),
+                number: 37,
+                ..Default::default()
+            },
+        ])
+        .unwrap()
+        .with_side(vec![Card {
+            name: "墓穴の指名者".to_string(),
+            number: 3,
+            ..Default::default()
+        }])
+        .unwrap();
+
+        let plan = serde_json::from_str::<SidingPlan>(
+            r#"{
+                "name": "vs ティアラメンツ",
+                "in": [{"name": "墓穴の指名者", "number": 2}],
+                "out": [{"name": "増殖するG", "number": 2}]
+            }"#,
+        )
+        .unwrap();
+
+        let sided = plan.apply(&deck).unwrap();
+        assert_eq!(sided.size(), 40);
+        assert_eq!(sided.number_of("墓穴の指名者"), 2);
+        assert_eq!(sided.number_of("増殖するG"), 35);
+        assert_eq!(sided.side_size(), 3);
+
+        // 抜くカードが足りない場合
+        let invalid = SidingPlan {
+            cards_out: vec![SideCard {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 4,
+                extra: false,
+            }],
+            ..plan.clone()
+        };
+        assert!(invalid.apply(&deck).is_err());
+
+        // 入れる枚数と抜く枚数が異なる場合
+        let too_many = SidingPlan {
+            cards_out: Vec::new(),
+            ..plan.clone()
+        };
+        assert!(too_many.apply(&deck).is_err());
+
+        // 1戦目のメインデッキが60枚を超える場合
+        let deck_61 = deck
+            .with_cards(vec![Card {
+                name: "増殖するG".to_string(),
+                number: 61,
+                ..Default::default()
+            }])
+            .unwrap();
+        assert!(plan.apply(&deck_61).is_err());
+
+        // エクストラデッキのカードはエクストラデッキと入れ替える
+        let deck = deck
+            .with_extra(vec![Card {
+                name: "神聖魔皇后セレーネ".to_string(),
+                number: 1,
+                ..Default::default()
+            }])
+            .unwrap()
+            .with_side(vec![Card {
+                name: "I：Pマスカレーナ".to_string(),
+                number: 1,
+                ..Default::default()
+            }])
+            .unwrap();
+        let extra_plan = SidingPlan {
+            cards_in: vec![SideCard {
+                name: "I：Pマスカレーナ".to_string(),
+                number: 1,
+                extra: true,
+            }],
+            cards_out: vec![SideCard {
+                name: "神聖魔皇后セレーネ".to_string(),
+                number: 1,
+                extra: true,
+            }],
+            ..plan
+        };
+        let sided = extra_plan.apply(&deck).unwrap();
+        assert_eq!(sided.size(), 40);
+        assert!(sided.in_extra("I：Pマスカレーナ"));
+        assert!(!sided.in_extra("神聖魔皇后セレーネ"));
+        assert_eq!(sided.side()[0].name, "神聖魔皇后セレーネ");
+
+        // メインデッキのカードとしてエクストラデッキのカードは抜けない
+        let wrong_section = SidingPlan {
+            cards_out: vec![SideCard {
+                extra: false,
+                ..extra_plan.cards_out[0].clone()
+            }],
+            cards_in: vec![SideCard {
+                extra: false,
+                ..extra_plan.cards_in[0].clone()
+            }],
+            ..extra_plan
+        };
+        assert!(wrong_section.apply(&deck).is_err());
+    }
+}
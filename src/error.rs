@@ -1,16 +1,107 @@
-/// アプリケーション全体のエラー
-#[derive(Debug, thiserror::Error)]
-pub enum AppError {
-    /// jsonのデシリアライズに関するエラー
-    #[error(transparent)]
-    DeserializeError(#[from] serde_json::Error),
-    /// jsonのデータが有効でない場合のエラー
-    #[error("AppError::InvalidDataError: {0}")]
-    InvalidDataError(String),
-    /// 組み合わせ計算がオーバーフローした場合のエラー
-    #[error("AppError::OverflowCombinationError: overflowed combination.")]
-    OverflowCombinationError,
-    /// ファイルの読み込みエラー
-    #[error(transparent)]
-    IoError(#[from] std::io::Error),
-}
+use std::fmt;
+use std::path::PathBuf;
+
+/// デッキや初動パターンなど，一覧の一つの要素の不正な内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryIssue {
+    /// 一覧の中のインデックス
+    pub index: usize,
+    /// 要素の名前．取得できない場合はNone
+    pub name: Option<String>,
+    /// 不正なフィールド．特定できない場合はNone
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for EntryIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}]", self.index)?;
+        if let Some(name) = &self.name {
+            write!(f, " {name}")?;
+        }
+        if let Some(field) = &self.field {
+            write!(f, " `{field}`")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// 要素ごとのエラーを一行ずつ並べる．
+fn format_issues(issues: &[EntryIssue]) -> String {
+    issues
+        .iter()
+        .map(|issue| format!("\n    {issue}"))
+        .collect()
+}
+
+/// アプリケーション全体のエラー
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    /// jsonのデシリアライズに関するエラー
+    #[error(transparent)]
+    DeserializeError(#[from] serde_json::Error),
+    /// jsonファイルのデシリアライズに関するエラー．最初のエラーの位置と，一覧の場合は不正な全ての要素を含む
+    #[error("AppError::JsonFileError: {}:{line}:{column}: {message}{}", .path.display(), format_issues(.issues))]
+    JsonFileError {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+        issues: Vec<EntryIssue>,
+    },
+    /// jsonのデータが有効でない場合のエラー
+    #[error("AppError::InvalidDataError: {0}")]
+    InvalidDataError(String),
+    /// 初動パターンが有効でない場合のエラー．不正な全てのパターンを含む
+    #[error("AppError::InvalidPatternError: invalid init_patterns.{}", format_issues(.0))]
+    InvalidPatternError(Vec<EntryIssue>),
+    /// 初動パターンが一つも無い場合のエラー
+    #[error("AppError::EmptyPatternError: no init_patterns.")]
+    EmptyPatternError,
+    /// デッキの枚数が大会で使用できる範囲に無い場合のエラー
+    #[error("AppError::DeckSizeError: {section} deck has {size} cards (allowed: {min}-{max}).")]
+    DeckSizeError {
+        section: String,
+        size: usize,
+        min: usize,
+        max: usize,
+    },
+    /// カードデータベースにカードが無い場合のエラー
+    #[error("AppError::CardNotFoundError: not in card database: {0}")]
+    CardNotFoundError(String),
+    /// カードデータベースで名前が複数のカードに一致する場合のエラー
+    #[error("AppError::AmbiguousCardError: {name} matches multiple cards: {ids:?}")]
+    AmbiguousCardError { name: String, ids: Vec<u32> },
+    /// 組み合わせ計算がオーバーフローした場合のエラー
+    #[error("AppError::OverflowCombinationError: overflowed combination.")]
+    OverflowCombinationError,
+    /// 事前に計算したnCkの表の範囲外を参照した場合のエラー
+    #[error(
+        "AppError::BinomialTableRangeError: n = {n} is out of binomial table (max n = {max_n})."
+    )]
+    BinomialTableRangeError { n: u64, max_n: u64 },
+    /// 探索がキャンセルされた場合のエラー
+    #[error("AppError::CancelledError: search was cancelled.")]
+    CancelledError,
+    /// ファイルの読み込みエラー
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    /// ファイル名を含むファイルの読み込みエラー
+    #[error("AppError::ReadFileError: {}: {source}", .path.display())]
+    ReadFileError {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// csvファイルの読み込みエラー
+    #[error("AppError::CsvFileError: {}: {source}", .path.display())]
+    CsvFileError { path: PathBuf, source: csv::Error },
+    /// csvの書き込みに関するエラー
+    #[error(transparent)]
+    CsvError(#[from] csv::Error),
+    /// 初動パターンのファイルの内容が有効でない場合のエラー．`source`は検証のエラー
+    #[error("AppError::PatternFileError: {}: {source}", .path.display())]
+    PatternFileError {
+        path: PathBuf,
+        source: Box<AppError>,
+    },
+}
@@ -2,11 +2,16 @@ pub mod all_search_v1;
 pub use all_search_v1 as all_search;
 pub mod all_search_v2;
 
+pub mod analysis;
 pub mod combinations;
 pub mod domain;
 mod error;
+pub mod matcher;
+pub mod output;
 pub mod probability;
+pub mod progress;
 pub mod reader;
+pub mod report;
 pub mod sampling;
 
-pub use error::AppError;
+pub use error::{AppError, EntryIssue};
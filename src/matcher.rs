@@ -0,0 +1,357 @@
+use crate::domain::{validate_patterns, CardId, Deck, InitialPattern};
+use crate::AppError;
+
+/// ビットマスクの一語のビット数
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// パターンのスロットの最大数(first, second, third)．割り当ての作業領域を固定長にするために用いる．
+const MAX_SLOT_N: usize = 3;
+
+/// カードIDの集合．IDをビットの位置としたビットマスクで表す．
+#[derive(Debug, Clone, PartialEq, Eq, Default, Hash)]
+pub struct CardMask {
+    words: Vec<u64>,
+}
+
+impl CardMask {
+    /// `type_n`種類のカードを表せる空の集合
+    pub fn new(type_n: usize) -> Self {
+        Self {
+            words: vec![0; type_n.div_ceil(WORD_BITS)],
+        }
+    }
+
+    pub fn from_ids(type_n: usize, ids: &[CardId]) -> Self {
+        let mut mask = Self::new(type_n);
+        for id in ids.iter() {
+            mask.insert(*id);
+        }
+        mask
+    }
+
+    /// IDごとの枚数のうち，1枚以上のカードの集合
+    pub fn from_counts(counts: &[u8]) -> Self {
+        let mut mask = Self::new(counts.len());
+        for (i, n) in counts.iter().enumerate() {
+            if *n > 0 {
+                mask.insert(CardId(i as u16));
+            }
+        }
+        mask
+    }
+
+    pub fn insert(&mut self, id: CardId) {
+        self.words[id.index() / WORD_BITS] |= 1 << (id.index() % WORD_BITS);
+    }
+
+    pub fn contains(&self, id: CardId) -> bool {
+        self.words
+            .get(id.index() / WORD_BITS)
+            .is_some_and(|word| word & (1 << (id.index() % WORD_BITS)) != 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|word| *word == 0)
+    }
+
+    /// 共通のカードがあるかどうか
+    pub fn intersects(&self, other: &CardMask) -> bool {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .any(|(x, y)| x & y != 0)
+    }
+
+    /// 共通のカードのIDを昇順に取得する．
+    pub fn intersection<'a>(&'a self, other: &'a CardMask) -> impl Iterator<Item = CardId> + 'a {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .enumerate()
+            .flat_map(|(word_i, (x, y))| {
+                let mut word = x & y;
+                std::iter::from_fn(move || {
+                    if word == 0 {
+                        return None;
+                    }
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    Some(CardId((word_i * WORD_BITS + bit) as u16))
+                })
+            })
+    }
+}
+
+/// 判定に用いる手札．IDごとの枚数と，手札にあるカードのビットマスク
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchHand {
+    counts: Vec<u8>,
+    mask: CardMask,
+}
+
+impl MatchHand {
+    /// IDごとの枚数で表した手札
+    pub fn new(counts: Vec<u8>) -> Self {
+        let mask = CardMask::from_counts(&counts);
+        Self { counts, mask }
+    }
+
+    pub fn counts(&self) -> &[u8] {
+        &self.counts
+    }
+
+    /// カードの枚数．デッキに無いIDの場合は0
+    pub fn number_of(&self, id: CardId) -> u8 {
+        self.counts.get(id.index()).copied().unwrap_or(0)
+    }
+}
+
+/// スロットごとのカードの集合にコンパイルしたパターン．デッキに無いカードはスロットから除く．
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompiledPattern {
+    pub slots: Vec<CardMask>,
+    pub level: u8,
+    /// 必要なエクストラデッキのカードが揃っているかどうか．揃っていない場合はマッチしない．
+    pub live: bool,
+}
+
+impl CompiledPattern {
+    fn new(deck: &Deck, pattern: &InitialPattern) -> Result<Self, AppError> {
+        let slots = [
+            Some(&pattern.first),
+            pattern.second.as_ref(),
+            pattern.third.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|slot| Ok(CardMask::from_ids(deck.len(), &deck.ids_of(slot)?)))
+        .collect::<Result<_, AppError>>()?;
+
+        Ok(Self {
+            slots,
+            level: pattern.level,
+            live: pattern.is_live(deck),
+        })
+    }
+
+    /// 各スロットに手札のカードを一枚ずつ割り当てられるかどうか．スロットとカードの二部グラフで，
+    /// カードの枚数を容量とした最大マッチングが全てのスロットを覆うかどうかで判定する(Hallの定理)．
+    pub fn is_match(&self, hand: &MatchHand) -> bool {
+        // 手札にカードが無いスロットがある場合は割り当てを探さない
+        if !self.live || self.slots.iter().any(|slot| !slot.intersects(&hand.mask)) {
+            return false;
+        }
+
+        let mut assigned: [Option<CardId>; MAX_SLOT_N] = [None; MAX_SLOT_N];
+        (0..self.slots.len()).all(|slot_i| {
+            let mut visited = 0_u32;
+            self.augment(slot_i, hand, &mut assigned, &mut visited)
+        })
+    }
+
+    /// `slot_i`番目のスロットから増加路を探し，見つかった場合は割り当てを更新する．
+    /// `visited`は探索済みのスロットのビットマスク
+    fn augment(
+        &self,
+        slot_i: usize,
+        hand: &MatchHand,
+        assigned: &mut [Option<CardId>; MAX_SLOT_N],
+        visited: &mut u32,
+    ) -> bool {
+        *visited |= 1 << slot_i;
+
+        for id in self.slots[slot_i].intersection(&hand.mask) {
+            // 枚数に空きがあるカードはそのまま割り当てる
+            let used_n = assigned.iter().filter(|used| **used == Some(id)).count();
+            if used_n < hand.number_of(id) as usize {
+                assigned[slot_i] = Some(id);
+                return true;
+            }
+
+            // 既にこのカードを割り当てたスロットを別のカードに付け替えられる場合
+            for other_i in 0..self.slots.len() {
+                if assigned[other_i] == Some(id)
+                    && *visited & (1 << other_i) == 0
+                    && self.augment(other_i, hand, assigned, visited)
+                {
+                    assigned[slot_i] = Some(id);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// 初動パターンをコンパイルしたもの．分析ごとに一度作成し，全探索・サンプリングの全ての手札の判定で使い回す．
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternMatcher {
+    pub patterns: Vec<CompiledPattern>,
+    pub max_level: u8,
+}
+
+impl PatternMatcher {
+    /// パターンを検証してコンパイルする．カードIDは`deck`のものを用いる．
+    pub fn new(deck: &Deck, patterns: &[InitialPattern]) -> Result<Self, AppError> {
+        let max_level = validate_patterns(patterns)?;
+
+        Ok(Self {
+            patterns: patterns
+                .iter()
+                .map(|pat| CompiledPattern::new(deck, pat))
+                .collect::<Result<_, _>>()?,
+            max_level,
+        })
+    }
+
+    /// 手札に対応したレベルを取得する．`hands`のいずれかがマッチしたパターンのインデックスごとに`on_match`を呼ぶ．
+    pub fn hand_level(
+        &self,
+        hands: &[MatchHand],
+        mut on_match: impl FnMut(usize),
+    ) -> Option<usize> {
+        // ハンドに対応したレベル
+        let mut level_flag: Option<usize> = None;
+
+        for (pat_i, pattern) in self.patterns.iter().enumerate() {
+            if hands.iter().any(|hand| pattern.is_match(hand)) {
+                on_match(pat_i);
+                level_flag = level_flag.max(Some(pattern.level as usize));
+            }
+        }
+
+        level_flag
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CardMask, MatchHand, PatternMatcher};
+    use crate::domain::{Card, CardId, Deck, InitialPattern, StringOrList};
+
+    #[test]
+    fn test_card_mask() {
+        let x = CardMask::from_ids(100, &[CardId(1), CardId(70), CardId(99)]);
+        let y = CardMask::from_ids(100, &[CardId(2), CardId(70), CardId(99)]);
+
+        assert!(x.contains(CardId(70)));
+        assert!(!x.contains(CardId(2)));
+        assert!(x.intersects(&y));
+        assert_eq!(
+            x.intersection(&y).collect::<Vec<_>>(),
+            vec![CardId(70), CardId(99)]
+        );
+        assert!(CardMask::new(100).is_empty());
+    }
+
+    #[test]
+    fn test_pattern_matcher() {
+        let deck = Deck::new(vec![
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "溟界の滓－ナイア".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "灰流うらら".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+
+        let patterns = vec![
+            InitialPattern {
+                name: "ヌル＋爬虫類".to_string(),
+                first: StringOrList::String("溟界の滓－ヌル".to_string()),
+                second: Some(StringOrList::List(vec![
+                    "溟界の滓－ヌル".to_string(),
+                    "溟界の滓－ナイア".to_string(),
+                    "存在しないカード".to_string(),
+                ])),
+                third: None,
+                level: 2,
+                extra: None,
+            },
+            InitialPattern {
+                name: "うらら".to_string(),
+                first: StringOrList::String("灰流うらら".to_string()),
+                second: None,
+                third: None,
+                level: 1,
+                extra: None,
+            },
+        ];
+        let matcher = PatternMatcher::new(&deck, &patterns).unwrap();
+        assert_eq!(matcher.max_level, 2);
+
+        for (counts, expected) in [
+            (vec![1, 1, 0], Some(2)),
+            (vec![2, 0, 1], Some(2)),
+            (vec![1, 0, 1], Some(1)),
+            (vec![0, 2, 0], None),
+        ] {
+            let hand = MatchHand::new(counts);
+            assert_eq!(matcher.hand_level(&[hand], |_| {}), expected);
+        }
+    }
+
+    #[test]
+    fn test_slot_assignment() {
+        // 16種類の爬虫類を3つのスロットに割り当てる
+        let reptiles = (0..16).map(|i| format!("爬虫類{i}")).collect::<Vec<_>>();
+        let deck = Deck::new(
+            reptiles
+                .iter()
+                .map(|name| Card {
+                    name: name.to_string(),
+                    number: 2,
+                    ..Default::default()
+                })
+                .collect(),
+        )
+        .unwrap();
+
+        let slot = StringOrList::List(reptiles.clone());
+        let patterns = vec![
+            InitialPattern {
+                name: "爬虫類3枚".to_string(),
+                first: slot.clone(),
+                second: Some(slot.clone()),
+                third: Some(slot),
+                level: 2,
+                extra: None,
+            },
+            InitialPattern {
+                name: "爬虫類0＋爬虫類0か1".to_string(),
+                first: StringOrList::List(vec![reptiles[0].to_string(), reptiles[1].to_string()]),
+                second: Some(StringOrList::String(reptiles[0].to_string())),
+                third: None,
+                level: 1,
+                extra: None,
+            },
+        ];
+        let matcher = PatternMatcher::new(&deck, &patterns).unwrap();
+
+        let hand = |ids: &[usize]| {
+            let mut counts = vec![0; 16];
+            for i in ids.iter() {
+                counts[*i] += 1;
+            }
+            MatchHand::new(counts)
+        };
+
+        // 最初のスロットに爬虫類0を割り当てた後，爬虫類1に付け替える
+        assert!(matcher.patterns[1].is_match(&hand(&[0, 1])));
+        assert!(!matcher.patterns[1].is_match(&hand(&[1, 1])));
+
+        assert!(matcher.patterns[0].is_match(&hand(&[3, 3, 15])));
+        assert!(matcher.patterns[0].is_match(&hand(&[0, 1, 7, 7])));
+        assert!(!matcher.patterns[0].is_match(&hand(&[4, 9])));
+    }
+}
@@ -0,0 +1,441 @@
+use crate::all_search::AllSearchResult;
+use crate::all_search_v2::ExactSearchResult;
+use crate::domain::{Deck, InitialPattern};
+use crate::AppError;
+
+use serde::Serialize;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+
+/// 確率の計算に用いたエンジン
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Engine {
+    /// `probability::pattern_prob`による計算
+    Probability,
+    /// `probability::pattern_prob_log`による対数空間での計算
+    LogProbability,
+    /// `all_search_v1`による全探索
+    AllSearchV1,
+    /// `all_search_v2`による全探索
+    AllSearchV2,
+    /// `sampling`によるランダムドロー
+    Sampling,
+    /// `all_search_v2::all_search_pattern_exact`による多倍長の全探索
+    Exact,
+}
+
+impl Engine {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Probability => "probability",
+            Self::LogProbability => "log_probability",
+            Self::AllSearchV1 => "all_search_v1",
+            Self::AllSearchV2 => "all_search_v2",
+            Self::Sampling => "sampling",
+            Self::Exact => "exact",
+        }
+    }
+}
+
+/// 計算の設定
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct Setting {
+    pub draw_n: u64,
+    /// ランダムドローの試行回数．サンプリング以外ではNone
+    pub trial_n: Option<usize>,
+}
+
+/// パターンごとの結果
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct PatternResult {
+    pub name: String,
+    pub level: u8,
+    pub prob: f64,
+    /// 既約分数で表した厳密な確率．厳密な計算以外ではNone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fraction: Option<String>,
+}
+
+/// レベルごとの結果．そのレベル以上のパターンにマッチする確率
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct LevelResult {
+    pub level: usize,
+    pub prob: f64,
+    /// 既約分数で表した厳密な確率．厳密な計算以外ではNone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fraction: Option<String>,
+}
+
+/// 出力用の計算結果．パターン名やレベル，計算条件を含む．
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct AnalysisResult {
+    pub engine: Engine,
+    /// デッキの内容のハッシュ．カードの順番や同名カードの分け方に依らない．エクストラ・サイドデッキやカードの効果なども含む
+    pub deck_hash: String,
+    pub setting: Setting,
+    pub patterns: Vec<PatternResult>,
+    pub levels: Vec<LevelResult>,
+}
+
+impl AnalysisResult {
+    /// 全探索・ランダムドローの結果から作成する．
+    pub fn new(
+        engine: Engine,
+        deck: &Deck,
+        patterns: &[InitialPattern],
+        setting: Setting,
+        result: &AllSearchResult,
+    ) -> Self {
+        Self {
+            engine,
+            deck_hash: deck_hash(deck),
+            setting,
+            patterns: patterns
+                .iter()
+                .zip(result.pattern_result.iter())
+                .map(|(pat, prob)| PatternResult {
+                    name: pat.name.to_string(),
+                    level: pat.level,
+                    prob: *prob,
+                    fraction: None,
+                })
+                .collect(),
+            levels: result
+                .level_result
+                .iter()
+                .enumerate()
+                .map(|(level, prob)| LevelResult {
+                    level,
+                    prob: *prob,
+                    fraction: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// 多倍長の全探索の結果から作成する．確率に加えて既約分数を持つ．
+    pub fn from_exact(
+        deck: &Deck,
+        patterns: &[InitialPattern],
+        setting: Setting,
+        result: &ExactSearchResult,
+    ) -> Self {
+        let mut analysis_result =
+            Self::new(Engine::Exact, deck, patterns, setting, &result.to_f64());
+
+        for (pat, fraction) in analysis_result
+            .patterns
+            .iter_mut()
+            .zip(result.pattern_result.iter())
+        {
+            pat.fraction = Some(fraction.to_string());
+        }
+        for (level, fraction) in analysis_result
+            .levels
+            .iter_mut()
+            .zip(result.level_result.iter())
+        {
+            level.fraction = Some(fraction.to_string());
+        }
+
+        analysis_result
+    }
+
+    /// パターンごとの確率のみから作成する．レベルごとの結果は空となる．
+    pub fn from_pattern_probs(
+        engine: Engine,
+        deck: &Deck,
+        patterns: &[InitialPattern],
+        setting: Setting,
+        pattern_probs: &[f64],
+    ) -> Self {
+        Self::new(
+            engine,
+            deck,
+            patterns,
+            setting,
+            &AllSearchResult {
+                pattern_result: pattern_probs.to_vec(),
+                level_result: Vec::new(),
+            },
+        )
+    }
+
+    /// 整形したJSONを書き込む．
+    pub fn write_json<W: Write>(&self, writer: W) -> Result<(), AppError> {
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// 一行に一つのパターンかレベルの確率を持つヘッダー付きのCSVを書き込む．列は`CsvRow`のフィールド
+    pub fn write_csv<W: Write>(&self, writer: W) -> Result<(), AppError> {
+        let mut writer = csv::Writer::from_writer(writer);
+
+        for pat in self.patterns.iter() {
+            writer.serialize(self.csv_row(
+                "pattern",
+                Some(&pat.name),
+                pat.level as usize,
+                pat.prob,
+                pat.fraction.as_deref(),
+            ))?;
+        }
+        for level in self.levels.iter() {
+            writer.serialize(self.csv_row(
+                "level",
+                None,
+                level.level,
+                level.prob,
+                level.fraction.as_deref(),
+            ))?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn csv_row<'a>(
+        &'a self,
+        kind: &'static str,
+        name: Option<&'a str>,
+        level: usize,
+        prob: f64,
+        fraction: Option<&'a str>,
+    ) -> CsvRow<'a> {
+        CsvRow {
+            engine: self.engine,
+            deck_hash: &self.deck_hash,
+            draw_n: self.setting.draw_n,
+            trial_n: self.setting.trial_n,
+            kind,
+            name,
+            level,
+            prob,
+            fraction,
+        }
+    }
+}
+
+/// CSVの一行．`kind`が`pattern`の場合はパターンの確率，`level`の場合はレベルの確率で，`name`は空となる．
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    engine: Engine,
+    deck_hash: &'a str,
+    draw_n: u64,
+    /// サンプリング以外では空
+    trial_n: Option<usize>,
+    kind: &'static str,
+    name: Option<&'a str>,
+    level: usize,
+    prob: f64,
+    /// 厳密な計算以外では空
+    fraction: Option<&'a str>,
+}
+
+/// FNV-1a 64bit．整数はリトルエンディアンの8バイトとして書き込むため，環境に依らない．
+struct Fnv1aHasher(u64);
+
+impl Hasher for Fnv1aHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+/// デッキの内容のハッシュ(FNV-1a 64bit)．メイン・エクストラ・サイドデッキのそれぞれをカード名でソートし，
+/// 枚数に加えて効果やサーチ先，カードの情報も含めて計算するため，環境やカードの並び順に依らず安定している．
+pub fn deck_hash(deck: &Deck) -> String {
+    let mut hasher = Fnv1aHasher(0xcbf29ce484222325);
+    for (section, cards) in [
+        ("main", deck.cards()),
+        ("extra", deck.extra()),
+        ("side", deck.side()),
+    ] {
+        let mut cards = cards.iter().collect::<Vec<_>>();
+        cards.sort_by(|a, b| a.name.cmp(&b.name));
+        (section, cards).hash(&mut hasher);
+    }
+
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{deck_hash, AnalysisResult, Engine, Setting};
+    use crate::all_search::AllSearchResult;
+    use crate::all_search_v2::ExactSearchResult;
+    use crate::domain::{Card, CardMeta, Deck, InitialPattern, StringOrList};
+
+    use num_rational::BigRational;
+
+    fn card(name: &str, number: u8) -> Card {
+        Card {
+            name: name.to_string(),
+            number,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_deck_hash() {
+        let deck = Deck::new(vec![card("溟界の滓－ヌル", 3), card("灰流うらら", 3)]).unwrap();
+        let reordered_deck = Deck::new(vec![
+            card("灰流うらら", 3),
+            card("溟界の滓－ヌル", 2),
+            card("溟界の滓－ヌル", 1),
+        ])
+        .unwrap();
+
+        assert_eq!(deck_hash(&deck), deck_hash(&reordered_deck));
+        assert_ne!(
+            deck_hash(&deck),
+            deck_hash(&Deck::new(vec![card("溟界の滓－ヌル", 3)]).unwrap())
+        );
+
+        // エクストラデッキやサイドデッキの違い
+        let with_extra = deck
+            .clone()
+            .with_extra(vec![card("アクセスコード・トーカー", 1)])
+            .unwrap();
+        let with_side = deck
+            .clone()
+            .with_side(vec![card("アクセスコード・トーカー", 1)])
+            .unwrap();
+        assert_ne!(deck_hash(&deck), deck_hash(&with_extra));
+        assert_ne!(deck_hash(&deck), deck_hash(&with_side));
+        assert_ne!(deck_hash(&with_extra), deck_hash(&with_side));
+
+        // 枚数が同じでもカードの情報が異なる
+        let with_acts_as = Deck::new(vec![
+            Card {
+                acts_as: Some(StringOrList::String("灰流うらら".to_string())),
+                ..card("溟界の滓－ヌル", 3)
+            },
+            card("灰流うらら", 3),
+        ])
+        .unwrap();
+        let with_meta = Deck::new(vec![
+            Card {
+                meta: CardMeta {
+                    tags: vec!["手札誘発".to_string()],
+                    ..Default::default()
+                },
+                ..card("灰流うらら", 3)
+            },
+            card("溟界の滓－ヌル", 3),
+        ])
+        .unwrap();
+        assert_ne!(deck_hash(&deck), deck_hash(&with_acts_as));
+        assert_ne!(deck_hash(&deck), deck_hash(&with_meta));
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let patterns = vec![InitialPattern {
+            name: "ヌル,任意".to_string(),
+            first: StringOrList::String("溟界の滓－ヌル".to_string()),
+            second: None,
+            third: None,
+            level: 1,
+            extra: None,
+        }];
+        let result = AnalysisResult::new(
+            Engine::AllSearchV2,
+            &Deck::new(Vec::new()).unwrap(),
+            &patterns,
+            Setting {
+                draw_n: 5,
+                trial_n: None,
+            },
+            &AllSearchResult {
+                pattern_result: vec![0.5],
+                level_result: vec![0.5, 0.5],
+            },
+        );
+
+        let mut csv = Vec::new();
+        result.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0],
+            "engine,deck_hash,draw_n,trial_n,kind,name,level,prob,fraction"
+        );
+        assert_eq!(
+            lines[1],
+            format!(
+                "all_search_v2,{},5,,pattern,\"ヌル,任意\",1,0.5,",
+                result.deck_hash
+            )
+        );
+        assert_eq!(
+            lines[3],
+            format!("all_search_v2,{},5,,level,,1,0.5,", result.deck_hash)
+        );
+
+        let mut json = Vec::new();
+        result.write_json(&mut json).unwrap();
+        let json = serde_json::from_slice::<serde_json::Value>(&json).unwrap();
+        assert_eq!(json["engine"], "all_search_v2");
+        assert_eq!(json["patterns"][0]["prob"], 0.5);
+        assert!(json["patterns"][0].get("fraction").is_none());
+    }
+
+    #[test]
+    fn test_from_exact() {
+        let patterns = vec![InitialPattern {
+            name: "ヌル".to_string(),
+            first: StringOrList::String("溟界の滓－ヌル".to_string()),
+            second: None,
+            third: None,
+            level: 1,
+            extra: None,
+        }];
+        let half = BigRational::new(1.into(), 2.into());
+        let result = AnalysisResult::from_exact(
+            &Deck::new(Vec::new()).unwrap(),
+            &patterns,
+            Setting {
+                draw_n: 5,
+                trial_n: None,
+            },
+            &ExactSearchResult {
+                pattern_result: vec![half.clone()],
+                level_result: vec![half.clone(), half],
+            },
+        );
+
+        assert_eq!(result.patterns[0].prob, 0.5);
+        assert_eq!(result.patterns[0].fraction.as_deref(), Some("1/2"));
+        let mut csv = Vec::new();
+        result.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap().lines().nth(1).unwrap(),
+            format!("exact,{},5,,pattern,ヌル,1,0.5,1/2", result.deck_hash)
+        );
+    }
+}
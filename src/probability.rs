@@ -1,137 +1,382 @@
-use crate::combinations::combination_n;
-use crate::domain::{Card, InitialPattern, StringOrList};
-use crate::AppError;
-
-use std::collections::HashSet;
-
-/// 初動パターンの確率計算
-pub fn pattern_prob(deck: &Vec<Card>, pat: &InitialPattern, draw_n: u64) -> Result<f64, AppError> {
-    let InitialPattern {
-        first,
-        second,
-        third,
-        ..
-    } = pat;
-
-    match (second, third) {
-        // 一枚パターン
-        (None, None) => single_pat_prob(deck, first, draw_n),
-        // 二枚パターン
-        (Some(second), None) => double_pat_prob(deck, first, second, draw_n),
-        // 三枚パターン
-        (Some(second), Some(third)) => triple_pat_prob(deck, first, second, third, draw_n),
-        _ => Err(AppError::InvalidDataError("invalid pattern".to_string())),
-    }
-}
-
-/// 1枚パターンの場合の場合の数．
-fn single_pat_prob(deck: &Vec<Card>, first: &StringOrList, draw_n: u64) -> Result<f64, AppError> {
-    let deck_card_n = deck
-        .iter()
-        .fold(0_u64, |acc, card| acc + card.number as u64);
-
-    let first_set: HashSet<&String> = first.as_set();
-
-    let first_n = deck
-        .iter()
-        .filter(|card| first_set.contains(&card.name))
-        .fold(0_u64, |acc, card| acc + card.number as u64);
-
-    let all_pattern =
-        combination_n(deck_card_n, draw_n).ok_or(AppError::OverflowCombinationError)?; // カードの引き方の全パターン数
-    let a_bar_pattern =
-        combination_n(deck_card_n - first_n, draw_n).ok_or(AppError::OverflowCombinationError)?; // ￢Aの場合の数
-
-    Ok((all_pattern - a_bar_pattern) as f64 / all_pattern as f64)
-}
-
-/// 2枚パターンの場合の場合の数．firstが一枚以上ありsecondが一枚以上ある集合について限定して(1枚も来なかった場合を用いて)計算しているため，
-/// firstとsecondに同じカードが含まれている場合はうまく計算できない．
-fn double_pat_prob(
-    deck: &Vec<Card>,
-    first: &StringOrList,
-    second: &StringOrList,
-    draw_n: u64,
-) -> Result<f64, AppError> {
-    let deck_card_n = deck
-        .iter()
-        .fold(0_u64, |acc, card| acc + card.number as u64);
-
-    let first_set: HashSet<&String> = first.as_set();
-    let second_set: HashSet<&String> = second.as_set();
-
-    let first_n = deck
-        .iter()
-        .filter(|card| first_set.contains(&card.name))
-        .fold(0_u64, |acc, card| acc + card.number as u64);
-
-    let second_n = deck
-        .iter()
-        .filter(|card| second_set.contains(&card.name))
-        .fold(0_u64, |acc, card| acc + card.number as u64);
-
-    let all_pattern =
-        combination_n(deck_card_n, draw_n).ok_or(AppError::OverflowCombinationError)?; // カードの引き方の全パターン数
-
-    || -> Option<f64> {
-        Some(
-            (all_pattern
-                - (combination_n(deck_card_n - first_n, draw_n)?
-                    + combination_n(deck_card_n - second_n, draw_n)?
-                    - combination_n(deck_card_n - (first_n + second_n), draw_n)?))
-                as f64
-                / all_pattern as f64,
-        )
-    }()
-    .ok_or(AppError::OverflowCombinationError)
-}
-
-/// 3枚パターンの場合の数．firstが一枚以上ありsecondが一枚以上ありthirdが一枚以上ある集合について限定して(1枚も来なかった場合を用いて)計算しているため，
-/// first, second, third内に同じカードが存在している場合はうまく計算できない．
-fn triple_pat_prob(
-    deck: &Vec<Card>,
-    first: &StringOrList,
-    second: &StringOrList,
-    third: &StringOrList,
-    draw_n: u64,
-) -> Result<f64, AppError> {
-    let deck_card_n = deck
-        .iter()
-        .fold(0_u64, |acc, card| acc + card.number as u64);
-
-    let first_set: HashSet<&String> = first.as_set();
-    let second_set: HashSet<&String> = second.as_set();
-    let third_set: HashSet<&String> = third.as_set();
-
-    let first_n = deck
-        .iter()
-        .filter(|card| first_set.contains(&card.name))
-        .fold(0_u64, |acc, card| acc + card.number as u64);
-    let second_n = deck
-        .iter()
-        .filter(|card| second_set.contains(&card.name))
-        .fold(0_u64, |acc, card| acc + card.number as u64);
-    let third_n = deck
-        .iter()
-        .filter(|card| third_set.contains(&card.name))
-        .fold(0_u64, |acc, card| acc + card.number as u64);
-
-    let all_pattern =
-        combination_n(deck_card_n, draw_n).ok_or(AppError::OverflowCombinationError)?; // カードの引き方の全パターン数
-
-    || -> Option<f64> {
-        Some(
-            (all_pattern
-                - (combination_n(deck_card_n - first_n, draw_n)?
-                    + combination_n(deck_card_n - second_n, draw_n)?
-                    + combination_n(deck_card_n - third_n, draw_n)?
-                    - combination_n(deck_card_n - (first_n + second_n), draw_n)?
-                    - combination_n(deck_card_n - (second_n + third_n), draw_n)?
-                    - combination_n(deck_card_n - (third_n + first_n), draw_n)?
-                    + combination_n(deck_card_n - (first_n + second_n + third_n), draw_n)?))
-                as f64
-                / all_pattern as f64,
-        )
-    }()
-    .ok_or(AppError::OverflowCombinationError)
-}
+mod hypergeometric;
+
+pub use hypergeometric::{Hypergeometric, MultivariateHypergeometric};
+
+use crate::combinations::{combination_big, ln_combination, validate_draw_n, BinomialTable};
+use crate::domain::{Deck, InitialPattern, StringOrList};
+use crate::AppError;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::Zero;
+use std::collections::HashSet;
+
+/// カードの`acts_as`(サーチカード)は確率計算で扱えないため，`acts_as`を持つカードがある場合はエラー
+fn reject_acts_as(deck: &Deck) -> Result<(), AppError> {
+    match deck.iter().find(|card| card.acts_as.is_some()) {
+        Some(card) => Err(AppError::InvalidDataError(format!(
+            "acts_as is not supported by probability engines (use all_search or sampling): {}",
+            card.name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// 初動パターンの確率計算．エクストラデッキの条件を満たさない場合は0．`acts_as`を持つカードがある場合はエラー．
+/// 場合の数はデッキの枚数まで作成した`table`から求める．
+pub fn pattern_prob(
+    deck: &Deck,
+    pat: &InitialPattern,
+    draw_n: u64,
+    table: &BinomialTable,
+) -> Result<f64, AppError> {
+    reject_acts_as(deck)?;
+    validate_draw_n(deck.size() as u64, draw_n)?;
+    if !pat.is_live(deck) {
+        return Ok(0.0);
+    }
+
+    let InitialPattern {
+        first,
+        second,
+        third,
+        ..
+    } = pat;
+
+    match (second, third) {
+        // 一枚パターン
+        (None, None) => single_pat_prob(deck, first, draw_n, table),
+        // 二枚パターン
+        (Some(second), None) => double_pat_prob(deck, first, second, draw_n, table),
+        // 三枚パターン
+        (Some(second), Some(third)) => triple_pat_prob(deck, first, second, third, draw_n, table),
+        _ => Err(AppError::InvalidDataError("invalid pattern".to_string())),
+    }
+}
+
+/// 1枚パターンの場合の場合の数．
+fn single_pat_prob(
+    deck: &Deck,
+    first: &StringOrList,
+    draw_n: u64,
+    table: &BinomialTable,
+) -> Result<f64, AppError> {
+    let deck_card_n = deck.size() as u64;
+
+    let first_set: HashSet<&String> = deck.resolve(first)?.into_iter().collect();
+
+    let first_n = deck.number_in(&first_set);
+
+    let all_pattern = table.get(deck_card_n, draw_n)?; // カードの引き方の全パターン数
+    let a_bar_pattern = table.get(deck_card_n - first_n, draw_n)?; // ￢Aの場合の数
+
+    Ok((all_pattern - a_bar_pattern) as f64 / all_pattern as f64)
+}
+
+/// 2枚パターンの場合の場合の数．firstが一枚以上ありsecondが一枚以上ある集合について限定して(1枚も来なかった場合を用いて)計算しているため，
+/// firstとsecondに同じカードが含まれている場合はうまく計算できない．
+fn double_pat_prob(
+    deck: &Deck,
+    first: &StringOrList,
+    second: &StringOrList,
+    draw_n: u64,
+    table: &BinomialTable,
+) -> Result<f64, AppError> {
+    let deck_card_n = deck.size() as u64;
+
+    let first_set: HashSet<&String> = deck.resolve(first)?.into_iter().collect();
+    let second_set: HashSet<&String> = deck.resolve(second)?.into_iter().collect();
+
+    let first_n = deck.number_in(&first_set);
+
+    let second_n = deck.number_in(&second_set);
+
+    let all_pattern = table.get(deck_card_n, draw_n)?; // カードの引き方の全パターン数
+
+    Ok((all_pattern
+        - (table.get(deck_card_n - first_n, draw_n)?
+            + table.get(deck_card_n - second_n, draw_n)?
+            - table.get(deck_card_n - (first_n + second_n), draw_n)?)) as f64
+        / all_pattern as f64)
+}
+
+/// 3枚パターンの場合の数．firstが一枚以上ありsecondが一枚以上ありthirdが一枚以上ある集合について限定して(1枚も来なかった場合を用いて)計算しているため，
+/// first, second, third内に同じカードが存在している場合はうまく計算できない．
+fn triple_pat_prob(
+    deck: &Deck,
+    first: &StringOrList,
+    second: &StringOrList,
+    third: &StringOrList,
+    draw_n: u64,
+    table: &BinomialTable,
+) -> Result<f64, AppError> {
+    let deck_card_n = deck.size() as u64;
+
+    let first_set: HashSet<&String> = deck.resolve(first)?.into_iter().collect();
+    let second_set: HashSet<&String> = deck.resolve(second)?.into_iter().collect();
+    let third_set: HashSet<&String> = deck.resolve(third)?.into_iter().collect();
+
+    let first_n = deck.number_in(&first_set);
+    let second_n = deck.number_in(&second_set);
+    let third_n = deck.number_in(&third_set);
+
+    let all_pattern = table.get(deck_card_n, draw_n)?; // カードの引き方の全パターン数
+
+    Ok((all_pattern
+        - (table.get(deck_card_n - first_n, draw_n)?
+            + table.get(deck_card_n - second_n, draw_n)?
+            + table.get(deck_card_n - third_n, draw_n)?
+            - table.get(deck_card_n - (first_n + second_n), draw_n)?
+            - table.get(deck_card_n - (second_n + third_n), draw_n)?
+            - table.get(deck_card_n - (third_n + first_n), draw_n)?
+            + table.get(deck_card_n - (first_n + second_n + third_n), draw_n)?)) as f64
+        / all_pattern as f64)
+}
+
+/// スロットごとのデッキ内の枚数．スロット間に同じカードが含まれている場合は計算できないため，
+/// パターン名と重複したカード名を含むエラー
+fn slot_numbers(deck: &Deck, pat: &InitialPattern) -> Result<Vec<u64>, AppError> {
+    let mut used: HashSet<&String> = HashSet::new();
+    pat.slots()?
+        .into_iter()
+        .map(|slot| {
+            let slot_set: HashSet<&String> = deck.resolve(slot)?.into_iter().collect();
+            if let Some(name) = slot_set.iter().find(|name| used.contains(*name)) {
+                return Err(AppError::InvalidDataError(format!(
+                    "Invalid init_pattern {}: {} is in multiple slots (not supported by probability engines).",
+                    pat.name, name
+                )));
+            }
+            used.extend(slot_set.iter());
+            Ok(deck.number_in(&slot_set))
+        })
+        .collect()
+}
+
+/// `pattern_prob`と同じ計算を多倍長整数で行い，確率を既約分数で求める．大きなデッキでもオーバーフローせず，
+/// 包除原理の足し引きで桁落ちしない．`acts_as`を持つカードがある場合はエラーとなる点は同じで，
+/// スロット間に同じカードが含まれている場合もエラーとなる．
+pub fn pattern_prob_exact(
+    deck: &Deck,
+    pat: &InitialPattern,
+    draw_n: u64,
+) -> Result<BigRational, AppError> {
+    reject_acts_as(deck)?;
+    let deck_card_n = deck.size() as u64;
+    validate_draw_n(deck_card_n, draw_n)?;
+    if !pat.is_live(deck) {
+        return Ok(BigRational::zero());
+    }
+
+    let slot_numbers = slot_numbers(deck, pat)?;
+
+    let all_pattern = BigInt::from(combination_big(deck_card_n, draw_n)); // カードの引き方の全パターン数
+
+    // スロットの部分集合ごとに，その部分集合のカードを1枚も引かない場合の数を包除原理で足し引きする．
+    let mut pattern_n = BigInt::zero();
+    for subset in 0..(1_usize << slot_numbers.len()) {
+        let excluded_n = slot_numbers
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| subset & (1 << i) != 0)
+            .fold(0_u64, |acc, (_, n)| acc + n);
+        let number = BigInt::from(combination_big(
+            deck_card_n.saturating_sub(excluded_n),
+            draw_n,
+        ));
+
+        if subset.count_ones() % 2 == 0 {
+            pattern_n += number;
+        } else {
+            pattern_n -= number;
+        }
+    }
+
+    Ok(BigRational::new(pattern_n, all_pattern))
+}
+
+/// 各スロットのカードを1枚以上ずつ引く確率を，スロットごとの枚数の組み合わせについて
+/// 多変量超幾何分布の確率を足し合わせて求める．全ての項が正のため包除原理のような桁落ちがなく，
+/// 対数空間で計算するためオーバーフローしない．`acts_as`を持つカードがある場合はエラーとなる点は`pattern_prob`と同じで，
+/// スロット間に同じカードが含まれている場合もエラーとなる．
+pub fn pattern_prob_log(deck: &Deck, pat: &InitialPattern, draw_n: u64) -> Result<f64, AppError> {
+    reject_acts_as(deck)?;
+    let deck_card_n = deck.size() as u64;
+    validate_draw_n(deck_card_n, draw_n)?;
+    if !pat.is_live(deck) {
+        return Ok(0.0);
+    }
+
+    let slot_numbers = slot_numbers(deck, pat)?;
+
+    let rest_n = deck_card_n - slot_numbers.iter().sum::<u64>();
+
+    let ln_all_pattern = ln_combination(deck_card_n, draw_n); // カードの引き方の全パターン数の対数
+    Ok(sum_slot_terms(
+        &slot_numbers,
+        rest_n,
+        draw_n,
+        0.0,
+        ln_all_pattern,
+    ))
+}
+
+/// 残りのスロットから1枚以上ずつ，残りを他のカードから`rest_draw_n`枚引く確率を再帰的に足し合わせる．
+/// `ln_weight`はそれまでのスロットの引き方の場合の数の対数．
+fn sum_slot_terms(
+    slot_numbers: &[u64],
+    rest_n: u64,
+    rest_draw_n: u64,
+    ln_weight: f64,
+    ln_all_pattern: f64,
+) -> f64 {
+    match slot_numbers.split_first() {
+        None => (ln_weight + ln_combination(rest_n, rest_draw_n) - ln_all_pattern).exp(),
+        Some((slot_n, rest_slots)) => (1..=std::cmp::min(*slot_n, rest_draw_n))
+            .map(|k| {
+                sum_slot_terms(
+                    rest_slots,
+                    rest_n,
+                    rest_draw_n - k,
+                    ln_weight + ln_combination(*slot_n, k),
+                    ln_all_pattern,
+                )
+            })
+            .sum(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{pattern_prob, pattern_prob_exact, pattern_prob_log};
+    use crate::combinations::BinomialTable;
+    use crate::domain::{Card, Deck, InitialPattern, StringOrList};
+    use crate::AppError;
+
+    use num_traits::ToPrimitive;
+
+    #[test]
+    fn test_pattern_prob_exact() {
+        let deck = Deck::new(vec![
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "溟界の滓－ナイア".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "灰流うらら".to_string(),
+                number: 34,
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+
+        let pat = InitialPattern {
+            name: "ヌル＋ナイア".to_string(),
+            first: StringOrList::String("溟界の滓－ヌル".to_string()),
+            second: Some(StringOrList::String("溟界の滓－ナイア".to_string())),
+            third: None,
+            level: 1,
+            extra: None,
+        };
+
+        let exact = pattern_prob_exact(&deck, &pat, 5).unwrap();
+        let table = BinomialTable::new(deck.size() as u64);
+        let prob = pattern_prob(&deck, &pat, 5, &table).unwrap();
+        assert!((exact.to_f64().unwrap() - prob).abs() < 1e-12);
+        let log = pattern_prob_log(&deck, &pat, 5).unwrap();
+        assert!((exact.to_f64().unwrap() - log).abs() < 1e-12);
+
+        // 120枚のデッキから30枚引く場合もオーバーフローしない
+        let large_deck = Deck::new(vec![
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "灰流うらら".to_string(),
+                number: 117,
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+        let single = InitialPattern {
+            second: None,
+            ..pat
+        };
+        let exact = pattern_prob_exact(&large_deck, &single, 30).unwrap();
+        assert!(exact.to_f64().unwrap() > 0.5);
+        let large_table = BinomialTable::new(large_deck.size() as u64);
+        let prob = pattern_prob(&large_deck, &single, 30, &large_table).unwrap();
+        assert!((exact.to_f64().unwrap() - prob).abs() < 1e-12);
+        // 表の範囲外のデッキはエラー
+        assert!(pattern_prob(&large_deck, &single, 30, &table).is_err());
+        let log = pattern_prob_log(&large_deck, &single, 30).unwrap();
+        assert!((exact.to_f64().unwrap() - log).abs() < 1e-12);
+
+        // サーチカードを含むデッキは扱えない
+        let searcher_deck = deck
+            .with_cards(vec![Card {
+                name: "灰流うらら".to_string(),
+                number: 40,
+                acts_as: Some(StringOrList::String("溟界の滓－ヌル".to_string())),
+                ..Default::default()
+            }])
+            .unwrap();
+        assert!(pattern_prob(&searcher_deck, &single, 5, &table).is_err());
+        assert!(pattern_prob_exact(&searcher_deck, &single, 5).is_err());
+        assert!(pattern_prob_log(&searcher_deck, &single, 5).is_err());
+    }
+
+    #[test]
+    fn test_overlapping_slots() {
+        let deck = Deck::new(vec![
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "溟界の滓－ナイア".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "灰流うらら".to_string(),
+                number: 34,
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+        let pat = InitialPattern {
+            name: "溟界2枚".to_string(),
+            first: StringOrList::List(vec![
+                "溟界の滓－ヌル".to_string(),
+                "溟界の滓－ナイア".to_string(),
+            ]),
+            second: Some(StringOrList::String("溟界の滓－ナイア".to_string())),
+            third: None,
+            level: 1,
+            extra: None,
+        };
+
+        for result in [
+            pattern_prob_log(&deck, &pat, 5).map(|_| ()),
+            pattern_prob_exact(&deck, &pat, 5).map(|_| ()),
+        ] {
+            match result {
+                Err(AppError::InvalidDataError(message)) => {
+                    assert!(message.contains("溟界2枚"));
+                    assert!(message.contains("溟界の滓－ナイア"));
+                }
+                _ => panic!("overlapping slots must be rejected"),
+            }
+        }
+    }
+}
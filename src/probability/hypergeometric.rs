@@ -0,0 +1,345 @@
+use crate::combinations::{hypergeometric_pmf, ln_combination, validate_draw_n};
+use crate::domain::{is_query, CardGroup, CardQuery, Deck};
+use crate::AppError;
+
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+/// デッキに含まれる`group`のカードの枚数．デッキに無いカード名や不正なクエリが含まれている場合はエラー
+fn group_card_n(deck: &Deck, group: &CardGroup) -> Result<u64, AppError> {
+    for name in group.cards.as_vec() {
+        if is_query(name) {
+            name.parse::<CardQuery>()?;
+        } else if deck.index_of(name).is_none() {
+            return Err(AppError::InvalidDataError(format!(
+                "Unknown card in group {}: {}",
+                group.name, name
+            )));
+        }
+    }
+
+    Ok(deck.number_in(&deck.resolve(&group.cards)?.into_iter().collect()))
+}
+
+/// 超幾何分布．`success_n`枚を含む`population_n`枚のデッキから`draw_n`枚引いたときに含まれる枚数の分布
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hypergeometric {
+    pub population_n: u64,
+    pub success_n: u64,
+    pub draw_n: u64,
+}
+
+impl Hypergeometric {
+    /// デッキの枚数を超えるドロー数や，デッキの枚数を超える`success_n`はエラー
+    pub fn new(population_n: u64, success_n: u64, draw_n: u64) -> Result<Self, AppError> {
+        validate_draw_n(population_n, draw_n)?;
+        if success_n > population_n {
+            return Err(AppError::InvalidDataError(format!(
+                "Invalid hypergeometric distribution: population {population_n}, success {success_n}, draw {draw_n}"
+            )));
+        }
+        Ok(Self {
+            population_n,
+            success_n,
+            draw_n,
+        })
+    }
+
+    /// デッキから`draw_n`枚引いたときに含まれる`group`のカードの枚数の分布
+    pub fn from_group(deck: &Deck, group: &CardGroup, draw_n: u64) -> Result<Self, AppError> {
+        let population_n = deck.size() as u64;
+        Self::new(population_n, group_card_n(deck, group)?, draw_n)
+    }
+
+    /// 取り得る枚数の最大値
+    pub fn max_k(&self) -> u64 {
+        std::cmp::min(self.success_n, self.draw_n)
+    }
+
+    /// ちょうど`k`枚含まれる確率
+    pub fn pmf(&self, k: u64) -> f64 {
+        hypergeometric_pmf(self.population_n, self.success_n, self.draw_n, k)
+    }
+
+    /// `k`枚以下含まれる確率
+    pub fn cdf(&self, k: u64) -> f64 {
+        self.range_prob(0..=k)
+    }
+
+    /// `k`枚以上含まれる確率．`1 - cdf(k - 1)`ではなく確率を足し合わせて求めるため桁落ちしない．
+    pub fn at_least(&self, k: u64) -> f64 {
+        self.range_prob(k..=self.max_k())
+    }
+
+    /// 含まれる枚数が`range`に収まる確率
+    pub fn range_prob(&self, range: RangeInclusive<u64>) -> f64 {
+        let max_k = std::cmp::min(*range.end(), self.max_k());
+        (*range.start()..=max_k)
+            .map(|k| self.pmf(k))
+            .sum::<f64>()
+            .min(1.0)
+    }
+
+    /// 0枚から`max_k`枚までそれぞれの確率
+    pub fn distribution(&self) -> Vec<f64> {
+        (0..=self.max_k()).map(|k| self.pmf(k)).collect()
+    }
+
+    /// 期待値
+    pub fn mean(&self) -> f64 {
+        self.draw_n as f64 * self.success_n as f64 / self.population_n as f64
+    }
+
+    /// 分散
+    pub fn variance(&self) -> f64 {
+        if self.population_n <= 1 {
+            return 0.0;
+        }
+        let population_n = self.population_n as f64;
+        let rate = self.success_n as f64 / population_n;
+        self.draw_n as f64 * rate * (1.0 - rate) * (population_n - self.draw_n as f64)
+            / (population_n - 1.0)
+    }
+}
+
+/// 多変量超幾何分布．グループごとに含まれる枚数の同時分布．グループに含まれないカードは残りとして扱う．
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultivariateHypergeometric {
+    pub population_n: u64,
+    /// グループごとのデッキ内の枚数
+    pub group_numbers: Vec<u64>,
+    pub draw_n: u64,
+}
+
+impl MultivariateHypergeometric {
+    /// デッキの枚数を超えるドロー数や，グループの枚数の合計がデッキの枚数を超える場合はエラー
+    pub fn new(population_n: u64, group_numbers: Vec<u64>, draw_n: u64) -> Result<Self, AppError> {
+        validate_draw_n(population_n, draw_n)?;
+        if group_numbers.iter().sum::<u64>() > population_n {
+            return Err(AppError::InvalidDataError(format!(
+                "Invalid hypergeometric distribution: population {population_n}, groups {group_numbers:?}, draw {draw_n}"
+            )));
+        }
+        Ok(Self {
+            population_n,
+            group_numbers,
+            draw_n,
+        })
+    }
+
+    /// デッキから`draw_n`枚引いたときに含まれる`groups`それぞれのカードの枚数の分布．
+    /// 同じカードが複数のグループに含まれている場合はエラー
+    pub fn from_groups(deck: &Deck, groups: &[CardGroup], draw_n: u64) -> Result<Self, AppError> {
+        let mut names: HashSet<&String> = HashSet::new();
+        for group in groups.iter() {
+            for name in deck.resolve(&group.cards)? {
+                if !names.insert(name) {
+                    return Err(AppError::InvalidDataError(format!(
+                        "Card in multiple groups: {name}"
+                    )));
+                }
+            }
+        }
+
+        let population_n = deck.size() as u64;
+        let group_numbers = groups
+            .iter()
+            .map(|group| group_card_n(deck, group))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::new(population_n, group_numbers, draw_n)
+    }
+
+    /// どのグループにも含まれないカードの枚数
+    fn rest_n(&self) -> u64 {
+        self.population_n - self.group_numbers.iter().sum::<u64>()
+    }
+
+    /// `group_i`番目のグループの周辺分布
+    pub fn marginal(&self, group_i: usize) -> Hypergeometric {
+        Hypergeometric {
+            population_n: self.population_n,
+            success_n: self.group_numbers[group_i],
+            draw_n: self.draw_n,
+        }
+    }
+
+    /// グループごとにちょうど`ks`枚ずつ含まれる確率
+    pub fn pmf(&self, ks: &[u64]) -> f64 {
+        let k_sum = ks.iter().sum::<u64>();
+        if ks.len() != self.group_numbers.len() || k_sum > self.draw_n {
+            return 0.0;
+        }
+
+        let ln_weight = self
+            .group_numbers
+            .iter()
+            .zip(ks.iter())
+            .map(|(n, k)| ln_combination(*n, *k))
+            .sum::<f64>();
+        (ln_weight + ln_combination(self.rest_n(), self.draw_n - k_sum)
+            - ln_combination(self.population_n, self.draw_n))
+        .exp()
+    }
+
+    /// グループごとに含まれる枚数がそれぞれ`ranges`に収まる確率．
+    /// 例えば`[2..=5, 1..=1]`は一つ目のグループを2枚以上，二つ目のグループをちょうど1枚引く確率
+    pub fn range_prob(&self, ranges: &[RangeInclusive<u64>]) -> f64 {
+        if ranges.len() != self.group_numbers.len() {
+            return 0.0;
+        }
+        let mut ks = Vec::with_capacity(ranges.len());
+        self.sum_range_prob(ranges, &mut ks).min(1.0)
+    }
+
+    /// 枚数の組み合わせを再帰的に列挙し，確率を足し合わせる．
+    fn sum_range_prob(&self, ranges: &[RangeInclusive<u64>], ks: &mut Vec<u64>) -> f64 {
+        let group_i = ks.len();
+        if group_i == ranges.len() {
+            return self.pmf(ks);
+        }
+
+        let rest_draw_n = self.draw_n - ks.iter().sum::<u64>();
+        let max_k = *[
+            *ranges[group_i].end(),
+            self.group_numbers[group_i],
+            rest_draw_n,
+        ]
+        .iter()
+        .min()
+        .unwrap_or(&0);
+
+        let mut prob = 0.0;
+        for k in *ranges[group_i].start()..=max_k {
+            ks.push(k);
+            prob += self.sum_range_prob(ranges, ks);
+            ks.pop();
+        }
+        prob
+    }
+
+    /// グループごとの期待値
+    pub fn mean(&self) -> Vec<f64> {
+        (0..self.group_numbers.len())
+            .map(|i| self.marginal(i).mean())
+            .collect()
+    }
+
+    /// グループごとの分散
+    pub fn variance(&self) -> Vec<f64> {
+        (0..self.group_numbers.len())
+            .map(|i| self.marginal(i).variance())
+            .collect()
+    }
+
+    /// 異なる二つのグループの共分散．同じグループの場合は分散
+    pub fn covariance(&self, i: usize, j: usize) -> f64 {
+        if i == j {
+            return self.marginal(i).variance();
+        }
+        if self.population_n <= 1 {
+            return 0.0;
+        }
+        let population_n = self.population_n as f64;
+        let draw_n = self.draw_n as f64;
+        -draw_n
+            * (self.group_numbers[i] as f64 / population_n)
+            * (self.group_numbers[j] as f64 / population_n)
+            * (population_n - draw_n)
+            / (population_n - 1.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Hypergeometric, MultivariateHypergeometric};
+    use crate::domain::{Card, CardGroup, Deck, StringOrList};
+    use crate::AppError;
+
+    fn deck() -> Deck {
+        Deck::new(vec![
+            Card {
+                name: "灰流うらら".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "増殖するG".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "溟界の滓－ナイア".to_string(),
+                number: 31,
+                ..Default::default()
+            },
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_hypergeometric() {
+        let group = CardGroup {
+            name: "手札誘発".to_string(),
+            cards: StringOrList::List(vec!["灰流うらら".to_string(), "増殖するG".to_string()]),
+        };
+        let dist = Hypergeometric::from_group(&deck(), &group, 5).unwrap();
+        assert_eq!(dist.success_n, 6);
+
+        // 34C5 / 40C5
+        assert!((dist.pmf(0) - 278256.0 / 658008.0).abs() < 1e-12);
+        assert!((dist.distribution().iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!((dist.cdf(1) + dist.at_least(2) - 1.0).abs() < 1e-12);
+        assert!((dist.mean() - 0.75).abs() < 1e-12);
+        // 5 * 6/40 * 34/40 * 35/39
+        assert!((dist.variance() - 5.0 * 0.15 * 0.85 * 35.0 / 39.0).abs() < 1e-12);
+
+        let unknown = CardGroup {
+            name: "不明".to_string(),
+            cards: StringOrList::String("存在しないカード".to_string()),
+        };
+        assert!(Hypergeometric::from_group(&deck(), &unknown, 5).is_err());
+
+        // デッキの枚数を超えるドロー数
+        match Hypergeometric::from_group(&deck(), &group, 41) {
+            Err(AppError::InvalidDataError(message)) => assert!(message.contains("draw 41")),
+            result => panic!("unexpected result: {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_multivariate_hypergeometric() {
+        let groups = vec![
+            CardGroup {
+                name: "手札誘発".to_string(),
+                cards: StringOrList::List(vec!["灰流うらら".to_string(), "増殖するG".to_string()]),
+            },
+            CardGroup {
+                name: "ヌル".to_string(),
+                cards: StringOrList::String("溟界の滓－ヌル".to_string()),
+            },
+        ];
+        let dist = MultivariateHypergeometric::from_groups(&deck(), &groups, 5).unwrap();
+        assert_eq!(dist.group_numbers, vec![6, 3]);
+
+        // 6C2 * 3C1 * 31C2 / 40C5
+        assert!((dist.pmf(&[2, 1]) - 15.0 * 3.0 * 465.0 / 658008.0).abs() < 1e-12);
+        assert!((dist.range_prob(&[0..=5, 0..=5]) - 1.0).abs() < 1e-12);
+
+        // 周辺分布と一致する
+        let marginal = dist.marginal(0);
+        assert!((dist.range_prob(&[2..=5, 0..=5]) - marginal.at_least(2)).abs() < 1e-12);
+
+        assert_eq!(dist.mean(), vec![0.75, 0.375]);
+        assert!(dist.covariance(0, 1) < 0.0);
+
+        let overlapped = vec![groups[0].clone(), groups[0].clone()];
+        assert!(MultivariateHypergeometric::from_groups(&deck(), &overlapped, 5).is_err());
+        assert!(MultivariateHypergeometric::from_groups(&deck(), &groups, 41).is_err());
+    }
+}
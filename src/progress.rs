@@ -0,0 +1,158 @@
+use crate::AppError;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// 探索の進捗を受け取る．ターミナルのプログレスバーやGUIなど，表示方法は利用側で実装する．
+pub trait ProgressObserver {
+    /// 全`total`件のうち`done`件の探索が終わった．`Progress`によって間引かれて呼ばれる．
+    fn on_progress(&mut self, done: u64, total: u64);
+
+    /// 探索が終わった．キャンセルされた場合は呼ばれない．
+    fn on_finish(&mut self) {}
+}
+
+impl<F: FnMut(u64, u64)> ProgressObserver for F {
+    fn on_progress(&mut self, done: u64, total: u64) {
+        self(done, total)
+    }
+}
+
+/// 探索のキャンセル．複製したトークンは状態を共有するため，別のスレッドからキャンセルできる．
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// 進捗の通知の最大回数．`interval`を指定しない場合は全体をこの回数に分けて通知する．
+const DEFAULT_NOTIFY_N: u64 = 1000;
+
+/// 探索の進捗の通知とキャンセルの確認．通知とキャンセルの確認は`interval`件ごとに間引いて行う．
+#[derive(Default)]
+pub struct Progress<'a> {
+    observer: Option<&'a mut dyn ProgressObserver>,
+    token: Option<CancellationToken>,
+    interval: Option<u64>,
+    total: u64,
+    done: u64,
+    /// 次に通知する件数
+    next_notify: u64,
+}
+
+impl<'a> Progress<'a> {
+    /// 進捗を通知せず，キャンセルもしない．
+    pub fn silent() -> Self {
+        Self::default()
+    }
+
+    /// `observer`に進捗を通知する．
+    pub fn new(observer: &'a mut dyn ProgressObserver) -> Self {
+        Self {
+            observer: Some(observer),
+            ..Default::default()
+        }
+    }
+
+    /// `token`がキャンセルされた場合に探索を中断し，`AppError::CancelledError`を返す．
+    pub fn with_token(self, token: CancellationToken) -> Self {
+        Self {
+            token: Some(token),
+            ..self
+        }
+    }
+
+    /// 通知とキャンセルの確認の間隔(件数)
+    pub fn with_interval(self, interval: u64) -> Self {
+        Self {
+            interval: Some(interval.max(1)),
+            ..self
+        }
+    }
+
+    /// 全`total`件の探索を始める．
+    pub(crate) fn start(&mut self, total: u64) -> Result<(), AppError> {
+        self.total = total;
+        self.done = 0;
+        self.next_notify = 0;
+        self.advance(0)
+    }
+
+    /// `n`件の探索が終わった．
+    pub(crate) fn advance(&mut self, n: u64) -> Result<(), AppError> {
+        self.done += n;
+        if self.done < self.next_notify {
+            return Ok(());
+        }
+        self.next_notify = self.done
+            + self
+                .interval
+                .unwrap_or_else(|| (self.total / DEFAULT_NOTIFY_N).max(1));
+
+        if self
+            .token
+            .as_ref()
+            .is_some_and(|token| token.is_cancelled())
+        {
+            return Err(AppError::CancelledError);
+        }
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_progress(self.done, self.total);
+        }
+        Ok(())
+    }
+
+    /// 全ての探索が終わった．
+    pub(crate) fn finish(&mut self) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_progress(self.total, self.total);
+            observer.on_finish();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CancellationToken, Progress};
+    use crate::AppError;
+
+    #[test]
+    fn test_progress() {
+        let mut calls = Vec::new();
+        let mut observer = |done: u64, total: u64| calls.push((done, total));
+        let mut progress = Progress::new(&mut observer).with_interval(3);
+
+        progress.start(10).unwrap();
+        for _ in 0..10 {
+            progress.advance(1).unwrap();
+        }
+        progress.finish();
+        drop(progress);
+
+        assert_eq!(calls, vec![(0, 10), (3, 10), (6, 10), (9, 10), (10, 10)]);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let token = CancellationToken::new();
+        let mut progress = Progress::silent().with_token(token.clone());
+
+        progress.start(10).unwrap();
+        progress.advance(1).unwrap();
+        token.cancel();
+        assert!(matches!(progress.advance(1), Err(AppError::CancelledError)));
+    }
+}
@@ -1,12 +1,222 @@
-use crate::error::AppError;
-
-use serde::de::DeserializeOwned;
-use std::fs::File;
-use std::path::Path;
-
-/// jsonのジェネリックなリーダー
-pub fn read_json<T: DeserializeOwned>(file_name: &Path) -> Result<T, AppError> {
-    let file = File::open(file_name)?;
-
-    Ok(serde_json::from_reader(file)?)
-}
+use crate::domain::{
+    validate_patterns, Card, CardDatabase, CardEntry, CardRow, Deck, DeckSections, InitialPattern,
+};
+use crate::error::{AppError, EntryIssue};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// ファイルを文字列として読み込む．エラーにはファイル名を含める．
+fn read_file(file_name: &Path) -> Result<String, AppError> {
+    fs::read_to_string(file_name).map_err(|source| AppError::ReadFileError {
+        path: file_name.to_path_buf(),
+        source,
+    })
+}
+
+/// デシリアライズのエラーをファイル名と位置を含むエラーに変換する．
+fn json_file_error(file_name: &Path, err: serde_json::Error, issues: Vec<EntryIssue>) -> AppError {
+    AppError::JsonFileError {
+        path: file_name.to_path_buf(),
+        line: err.line(),
+        column: err.column(),
+        message: err.to_string(),
+        issues,
+    }
+}
+
+/// serdeのエラーメッセージから不正なフィールド名を取り出す．
+fn issue_field(message: &str) -> Option<String> {
+    ["missing field `", "unknown field `", "duplicate field `"]
+        .iter()
+        .find_map(|prefix| message.strip_prefix(prefix))
+        .and_then(|rest| rest.split_once('`'))
+        .map(|(field, _)| field.to_string())
+}
+
+/// jsonのジェネリックなリーダー
+pub fn read_json<T: DeserializeOwned>(file_name: &Path) -> Result<T, AppError> {
+    let json = read_file(file_name)?;
+    parse_json(file_name, &json)
+}
+
+/// 読み込んだjsonをデシリアライズする．`file_name`はエラーの表示に用いる．
+fn parse_json<T: DeserializeOwned>(file_name: &Path, json: &str) -> Result<T, AppError> {
+    serde_json::from_str(json).map_err(|err| json_file_error(file_name, err, Vec::new()))
+}
+
+/// jsonの配列のリーダー．不正な要素がある場合は，最初のエラーの位置に加えて不正な全ての要素をまとめて返す．
+pub fn read_json_list<T: DeserializeOwned>(file_name: &Path) -> Result<Vec<T>, AppError> {
+    let json = read_file(file_name)?;
+    parse_json_list(file_name, &json)
+}
+
+/// 読み込んだjsonの配列をデシリアライズする．`file_name`はエラーの表示に用いる．
+fn parse_json_list<T: DeserializeOwned>(file_name: &Path, json: &str) -> Result<Vec<T>, AppError> {
+    serde_json::from_str(json).map_err(|err| {
+        // jsonとして読める配列の場合は，要素ごとにデシリアライズしてエラーを集める
+        let issues = match serde_json::from_str::<Vec<Value>>(json) {
+            Ok(values) => values
+                .into_iter()
+                .enumerate()
+                .filter_map(|(index, value)| {
+                    let name = value
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .map(str::to_string);
+                    let message = serde_json::from_value::<T>(value).err()?.to_string();
+                    Some(EntryIssue {
+                        index,
+                        name,
+                        field: issue_field(&message),
+                        message,
+                    })
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        json_file_error(file_name, err, issues)
+    })
+}
+
+/// 初動パターンのjsonファイルを読み込み，検証する．検証のエラーにはファイル名を含める．
+pub fn read_patterns(file_name: &Path) -> Result<Vec<InitialPattern>, AppError> {
+    let patterns = read_json_list::<InitialPattern>(file_name)?;
+    validate_patterns(&patterns).map_err(|source| AppError::PatternFileError {
+        path: file_name.to_path_buf(),
+        source: Box::new(source),
+    })?;
+    Ok(patterns)
+}
+
+/// デッキのjsonファイルを読み込み，検証する．メインデッキのみの配列と，
+/// `main`と`extra`に分けたオブジェクトのどちらの形式も読み込める．
+pub fn read_deck(file_name: &Path) -> Result<Deck, AppError> {
+    let json = read_file(file_name)?;
+
+    if json.trim_start().starts_with('[') {
+        Deck::new(parse_json_list::<Card>(file_name, &json)?)
+    } else {
+        Deck::try_from(parse_json::<DeckSections>(file_name, &json)?)
+    }
+}
+
+/// カードデータベースを読み込む．拡張子が`csv`の場合はヘッダー付きのcsv，それ以外は`CardEntry`のjsonの配列として読み込む．
+pub fn read_card_database(file_name: &Path) -> Result<CardDatabase, AppError> {
+    let text = read_file(file_name)?;
+
+    let entries = match file_name.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => csv::Reader::from_reader(text.as_bytes())
+            .deserialize::<CardRow>()
+            .map(|row| row.map(CardEntry::from))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|source| AppError::CsvFileError {
+                path: file_name.to_path_buf(),
+                source,
+            })?,
+        _ => parse_json_list(file_name, &text)?,
+    };
+
+    CardDatabase::new(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_card_database, read_json_list, read_patterns};
+    use crate::domain::InitialPattern;
+    use crate::AppError;
+
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_read_json_list() {
+        let file_name = std::env::temp_dir().join("deck_analyzer_test_read_json_list.json");
+        std::fs::write(
+            &file_name,
+            r#"[
+    {"name": "ヌル", "first": "溟界の滓－ヌル", "level": 1},
+    {"name": "ナイア", "first": "溟界の滓－ナイア"},
+    {"name": "うらら", "first": "灰流うらら", "level": "1"}
+]"#,
+        )
+        .unwrap();
+
+        let err = read_json_list::<InitialPattern>(&file_name).unwrap_err();
+        std::fs::remove_file(&file_name).unwrap();
+
+        match err {
+            AppError::JsonFileError {
+                path, line, issues, ..
+            } => {
+                assert_eq!(path, file_name);
+                assert_eq!(line, 3);
+                assert_eq!(issues.len(), 2);
+                assert_eq!(issues[0].index, 1);
+                assert_eq!(issues[0].name.as_deref(), Some("ナイア"));
+                assert_eq!(issues[0].field.as_deref(), Some("level"));
+                assert_eq!(issues[1].index, 2);
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+
+        let err = read_json_list::<InitialPattern>(&PathBuf::from("not_found.json")).unwrap_err();
+        assert!(matches!(err, AppError::ReadFileError { .. }));
+    }
+
+    #[test]
+    fn test_read_patterns() {
+        let file_name = std::env::temp_dir().join("deck_analyzer_test_read_patterns.json");
+        std::fs::write(
+            &file_name,
+            r#"[
+    {"name": "ヌル", "first": "溟界の滓－ヌル", "level": 1},
+    {"name": "空", "first": [], "level": 1}
+]"#,
+        )
+        .unwrap();
+
+        let err = read_patterns(&file_name).unwrap_err();
+        std::fs::remove_file(&file_name).unwrap();
+
+        match err {
+            AppError::PatternFileError { path, source } => {
+                assert_eq!(path, file_name);
+                match *source {
+                    AppError::InvalidPatternError(issues) => {
+                        assert_eq!(issues.len(), 1);
+                        assert_eq!(issues[0].index, 1);
+                    }
+                    err => panic!("unexpected error: {err}"),
+                }
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn test_read_card_database() {
+        let file_name = std::env::temp_dir().join("deck_analyzer_test_read_card_database.csv");
+        std::fs::write(
+            &file_name,
+            "id,name_ja,name_en,type,attribute,race,level,tags
+1,溟界の滓－ヌル,Ogdoadic Remnant Nunu,モンスター,闇,爬虫類,2,初動;溟界
+2,死者蘇生,Monster Reborn,魔法,,,,
+",
+        )
+        .unwrap();
+
+        let db = read_card_database(&file_name).unwrap();
+        std::fs::remove_file(&file_name).unwrap();
+
+        let nunu = db.lookup("Ogdoadic Remnant Nunu").unwrap();
+        assert_eq!(nunu.id, 1);
+        assert_eq!(nunu.meta.level, Some(2));
+        assert_eq!(nunu.meta.tags, vec!["初動", "溟界"]);
+        let reborn = db.lookup("2").unwrap();
+        assert_eq!(reborn.meta.race, None);
+        assert!(reborn.meta.tags.is_empty());
+    }
+}
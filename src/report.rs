@@ -0,0 +1,327 @@
+use crate::domain::{Card, InitialPattern, StringOrList};
+use crate::output::AnalysisResult;
+
+/// 先攻と後攻の計算結果をまとめたレポート．MarkdownとHTMLで出力する．
+pub struct Report<'a> {
+    pub title: String,
+    pub deck: &'a [Card],
+    pub patterns: &'a [InitialPattern],
+    /// 先攻(5枚ドロー)の結果
+    pub first: &'a AnalysisResult,
+    /// 後攻(6枚ドロー)の結果
+    pub second: &'a AnalysisResult,
+}
+
+/// パターンのスロットの表示
+fn format_slot(slot: Option<&StringOrList>) -> String {
+    match slot {
+        Some(slot) => slot
+            .as_vec()
+            .into_iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" / "),
+        None => "-".to_string(),
+    }
+}
+
+/// 確率の百分率表示
+fn format_prob(prob: f64) -> String {
+    format!("{:.2}%", prob * 100.0)
+}
+
+/// Markdownの表のセルとしてエスケープする．
+fn markdown_escape(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// HTMLのテキストとしてエスケープする．
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 先攻と後攻の確率を並べた横棒グラフのSVG
+fn svg_bar_chart(labels: &[String], first_probs: &[f64], second_probs: &[f64]) -> String {
+    const LABEL_WIDTH: usize = 240;
+    const BAR_WIDTH: usize = 400;
+    const BAR_HEIGHT: usize = 12;
+    const ROW_HEIGHT: usize = BAR_HEIGHT * 2 + 8;
+    const LEGEND_HEIGHT: usize = 20;
+
+    let width = LABEL_WIDTH + BAR_WIDTH + 80;
+    let height = LEGEND_HEIGHT + ROW_HEIGHT * labels.len();
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-size="12">"#
+    );
+    svg.push_str(&format!(
+        r##"<rect x="{LABEL_WIDTH}" y="2" width="12" height="12" fill="#4e79a7"/><text x="{}" y="12">先攻</text>"##,
+        LABEL_WIDTH + 16
+    ));
+    svg.push_str(&format!(
+        r##"<rect x="{}" y="2" width="12" height="12" fill="#f28e2b"/><text x="{}" y="12">後攻</text>"##,
+        LABEL_WIDTH + 64,
+        LABEL_WIDTH + 80
+    ));
+
+    for (i, label) in labels.iter().enumerate() {
+        let y = LEGEND_HEIGHT + ROW_HEIGHT * i;
+        svg.push_str(&format!(
+            r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#,
+            LABEL_WIDTH - 8,
+            y + BAR_HEIGHT + 4,
+            html_escape(label)
+        ));
+
+        for (j, (prob, color)) in [
+            (first_probs.get(i), "#4e79a7"),
+            (second_probs.get(i), "#f28e2b"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let prob = prob.copied().unwrap_or(0.0);
+            let bar_y = y + BAR_HEIGHT * j;
+            let bar_width = (prob * BAR_WIDTH as f64).round() as usize;
+            svg.push_str(&format!(
+                r#"<rect x="{LABEL_WIDTH}" y="{bar_y}" width="{bar_width}" height="{}" fill="{color}"/>"#,
+                BAR_HEIGHT - 1
+            ));
+            svg.push_str(&format!(
+                r#"<text x="{}" y="{}">{}</text>"#,
+                LABEL_WIDTH + bar_width + 4,
+                bar_y + BAR_HEIGHT - 2,
+                format_prob(prob)
+            ));
+        }
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+impl<'a> Report<'a> {
+    /// パターンの名前，先攻の確率，後攻の確率
+    fn pattern_rows(&self) -> Vec<(String, u8, f64, f64)> {
+        self.first
+            .patterns
+            .iter()
+            .zip(self.second.patterns.iter())
+            .map(|(first, second)| (first.name.to_string(), first.level, first.prob, second.prob))
+            .collect()
+    }
+
+    /// レベル，先攻の確率，後攻の確率
+    fn level_rows(&self) -> Vec<(usize, f64, f64)> {
+        self.first
+            .levels
+            .iter()
+            .zip(self.second.levels.iter())
+            .map(|(first, second)| (first.level, first.prob, second.prob))
+            .collect()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("# {}\n\n", self.title);
+
+        md.push_str("## デッキ\n\n| カード名 | 枚数 |\n| --- | ---: |\n");
+        for card in self.deck.iter() {
+            md.push_str(&format!(
+                "| {} | {} |\n",
+                markdown_escape(&card.name),
+                card.number
+            ));
+        }
+
+        md.push_str("\n## 初動パターン\n\n| パターン名 | レベル | first | second | third |\n| --- | ---: | --- | --- | --- |\n");
+        for pat in self.patterns.iter() {
+            md.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                markdown_escape(&pat.name),
+                pat.level,
+                markdown_escape(&format_slot(Some(&pat.first))),
+                markdown_escape(&format_slot(pat.second.as_ref())),
+                markdown_escape(&format_slot(pat.third.as_ref()))
+            ));
+        }
+
+        md.push_str(
+            "\n## パターンごとの確率\n\n| パターン名 | レベル | 先攻 | 後攻 |\n| --- | ---: | ---: | ---: |\n",
+        );
+        for (name, level, first_prob, second_prob) in self.pattern_rows() {
+            md.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                markdown_escape(&name),
+                level,
+                format_prob(first_prob),
+                format_prob(second_prob)
+            ));
+        }
+
+        md.push_str(
+            "\n## レベルごとの確率(そのレベル以上)\n\n| レベル | 先攻 | 後攻 |\n| ---: | ---: | ---: |\n",
+        );
+        for (level, first_prob, second_prob) in self.level_rows() {
+            md.push_str(&format!(
+                "| {} | {} | {} |\n",
+                level,
+                format_prob(first_prob),
+                format_prob(second_prob)
+            ));
+        }
+
+        md
+    }
+
+    /// 外部のファイルを参照しない単一のHTMLページ
+    pub fn to_html(&self) -> String {
+        let title = html_escape(&self.title);
+        let mut html = format!(
+            r#"<!DOCTYPE html>
+<html lang="ja">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; margin-bottom: 1em; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 8px; }}
+td.number {{ text-align: right; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+"#
+        );
+
+        html.push_str("<h2>デッキ</h2>\n<table>\n<tr><th>カード名</th><th>枚数</th></tr>\n");
+        for card in self.deck.iter() {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"number\">{}</td></tr>\n",
+                html_escape(&card.name),
+                card.number
+            ));
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("<h2>初動パターン</h2>\n<table>\n<tr><th>パターン名</th><th>レベル</th><th>first</th><th>second</th><th>third</th></tr>\n");
+        for pat in self.patterns.iter() {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"number\">{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                html_escape(&pat.name),
+                pat.level,
+                html_escape(&format_slot(Some(&pat.first))),
+                html_escape(&format_slot(pat.second.as_ref())),
+                html_escape(&format_slot(pat.third.as_ref()))
+            ));
+        }
+        html.push_str("</table>\n");
+
+        let pattern_rows = self.pattern_rows();
+        html.push_str("<h2>パターンごとの確率</h2>\n<table>\n<tr><th>パターン名</th><th>レベル</th><th>先攻</th><th>後攻</th></tr>\n");
+        for (name, level, first_prob, second_prob) in pattern_rows.iter() {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>\n",
+                html_escape(name),
+                level,
+                format_prob(*first_prob),
+                format_prob(*second_prob)
+            ));
+        }
+        html.push_str("</table>\n");
+        html.push_str(&svg_bar_chart(
+            &pattern_rows
+                .iter()
+                .map(|(name, ..)| name.to_string())
+                .collect::<Vec<_>>(),
+            &pattern_rows.iter().map(|row| row.2).collect::<Vec<_>>(),
+            &pattern_rows.iter().map(|row| row.3).collect::<Vec<_>>(),
+        ));
+
+        let level_rows = self.level_rows();
+        html.push_str("\n<h2>レベルごとの確率(そのレベル以上)</h2>\n<table>\n<tr><th>レベル</th><th>先攻</th><th>後攻</th></tr>\n");
+        for (level, first_prob, second_prob) in level_rows.iter() {
+            html.push_str(&format!(
+                "<tr><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>\n",
+                level,
+                format_prob(*first_prob),
+                format_prob(*second_prob)
+            ));
+        }
+        html.push_str("</table>\n");
+        html.push_str(&svg_bar_chart(
+            &level_rows
+                .iter()
+                .map(|(level, ..)| format!("レベル{level}"))
+                .collect::<Vec<_>>(),
+            &level_rows.iter().map(|row| row.1).collect::<Vec<_>>(),
+            &level_rows.iter().map(|row| row.2).collect::<Vec<_>>(),
+        ));
+
+        html.push_str("\n</body>\n</html>\n");
+        html
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Report;
+    use crate::all_search::AllSearchResult;
+    use crate::domain::{Card, Deck, InitialPattern, StringOrList};
+    use crate::output::{AnalysisResult, Engine, Setting};
+
+    #[test]
+    fn test_report() {
+        let deck = Deck::new(vec![Card {
+            name: "溟界の滓－ヌル".to_string(),
+            number: 3,
+            ..Default::default()
+        }])
+        .unwrap();
+        let patterns = vec![InitialPattern {
+            name: "ヌル<任意>".to_string(),
+            first: StringOrList::String("溟界の滓－ヌル".to_string()),
+            second: None,
+            third: None,
+            level: 1,
+            extra: None,
+        }];
+        let analysis_result = |draw_n: u64, prob: f64| {
+            AnalysisResult::new(
+                Engine::AllSearchV2,
+                &deck,
+                &patterns,
+                Setting {
+                    draw_n,
+                    trial_n: None,
+                },
+                &AllSearchResult {
+                    pattern_result: vec![prob],
+                    level_result: vec![prob, prob],
+                },
+            )
+        };
+        let first = analysis_result(5, 0.5);
+        let second = analysis_result(6, 0.6);
+
+        let report = Report {
+            title: "溟界".to_string(),
+            deck: &deck,
+            patterns: &patterns,
+            first: &first,
+            second: &second,
+        };
+
+        let md = report.to_markdown();
+        assert!(md.contains("| 溟界の滓－ヌル | 3 |"));
+        assert!(md.contains("| ヌル<任意> | 1 | 50.00% | 60.00% |"));
+
+        let html = report.to_html();
+        assert!(html.contains("<svg"));
+        assert!(html.contains("ヌル&lt;任意&gt;"));
+        assert!(!html.contains("ヌル<任意>"));
+    }
+}
@@ -1,103 +1,666 @@
-use crate::domain::Card;
-
-use rand::seq::SliceRandom;
-use rand::thread_rng;
-
-pub struct DeckInstance {
-    /// デッキの内容を表すVec．ドローの構造上一番上のカードが最後の要素となる．
-    deck_names: Vec<String>,
-    hands_names: Vec<String>,
-}
-
-impl DeckInstance {
-    /// O(a * n) (aはカード枚数上限)
-    pub fn new(deck: &[Card]) -> Self {
-        let mut deck_names = Vec::new();
-
-        for card in deck.iter() {
-            for _ in 0..card.number {
-                deck_names.push(card.name.to_string());
-            }
-        }
-
-        DeckInstance {
-            deck_names,
-            hands_names: Vec::new(),
-        }
-    }
-
-    /// デッキをシャッフルする．O(n)．
-    pub fn shuffle(&mut self) {
-        self.deck_names.shuffle(&mut thread_rng());
-    }
-
-    /// `draw_n`分ドローする．O(draw_n)．
-    pub fn draw(&mut self, draw_n: usize) {
-        for _ in 0..draw_n {
-            let deck_length = self.deck_names.len();
-            let draw_card = self.deck_names.swap_remove(deck_length - 1);
-            self.hands_names.push(draw_card);
-        }
-    }
-    pub fn deck(&self) -> &[String] {
-        &self.deck_names
-    }
-    pub fn hands(&self) -> &[String] {
-        &self.hands_names
-    }
-}
-
-#[cfg(test)]
-mod test {
-    use super::DeckInstance;
-    use crate::domain::Card;
-
-    use std::collections::HashSet;
-
-    #[test]
-    fn test_deck_instance() {
-        let deck = vec![
-            Card {
-                name: "強欲な壺".to_string(),
-                number: 3,
-            },
-            Card {
-                name: "ブラック・マジシャン".to_string(),
-                number: 3,
-            },
-            Card {
-                name: "幻獣王ガゼル".to_string(),
-                number: 3,
-            },
-            Card {
-                name: "暗黒騎士ガイア".to_string(),
-                number: 3,
-            },
-            Card {
-                name: "エルフの剣士".to_string(),
-                number: 3,
-            },
-        ];
-
-        let mut card_name_pool = HashSet::new();
-        card_name_pool.insert("強欲な壺".to_string());
-        card_name_pool.insert("ブラック・マジシャン".to_string());
-        card_name_pool.insert("幻獣王ガゼル".to_string());
-        card_name_pool.insert("暗黒騎士ガイア".to_string());
-        card_name_pool.insert("エルフの剣士".to_string());
-
-        let mut deck_instance = DeckInstance::new(&deck);
-        deck_instance.shuffle();
-
-        assert_eq!(deck_instance.deck().len(), 15);
-        assert_eq!(deck_instance.hands().len(), 0);
-
-        deck_instance.draw(5);
-        assert_eq!(deck_instance.deck().len(), 10);
-        assert_eq!(deck_instance.hands().len(), 5);
-
-        for card_name in deck_instance.deck() {
-            assert!(card_name_pool.contains(card_name));
-        }
-    }
-}
+use crate::all_search::{deck_substitutes, substitute_hands, AllSearchResult, MulliganResult};
+use crate::combinations::validate_draw_n;
+use crate::domain::{
+    fill_counts, CardEffect, CardId, Deck, EffectAction, InitialPattern, MulliganMethod,
+    MulliganPolicy,
+};
+use crate::matcher::{MatchHand, PatternMatcher};
+use crate::progress::Progress;
+use crate::AppError;
+
+use itertools::Itertools;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// 対象のカード名をIDに解決した効果の動作
+#[derive(Debug, Clone)]
+struct ResolvedAction {
+    action: EffectAction,
+    /// サーチ・めくる場合の対象．`targets`に書いた順
+    targets: Vec<CardId>,
+}
+
+/// 動作の対象をIDに解決した効果．試行ごとにカード名を引かないよう，`DeckInstance::new`で一度だけ解決する．
+#[derive(Debug, Clone)]
+struct ResolvedEffect {
+    cost: Vec<ResolvedAction>,
+    actions: Vec<ResolvedAction>,
+    once_per_turn: bool,
+    remains: bool,
+}
+
+impl ResolvedEffect {
+    /// 対象に不正なクエリがある場合はエラー
+    fn new(deck: &Deck, effect: &CardEffect) -> Result<Self, AppError> {
+        let resolve = |actions: &[EffectAction]| {
+            actions
+                .iter()
+                .map(|action| {
+                    Ok(ResolvedAction {
+                        action: action.clone(),
+                        targets: match action {
+                            EffectAction::Search { targets }
+                            | EffectAction::Excavate { targets, .. } => deck.ids_of(targets)?,
+                            _ => Vec::new(),
+                        },
+                    })
+                })
+                .collect::<Result<Vec<_>, AppError>>()
+        };
+
+        Ok(Self {
+            cost: resolve(&effect.cost)?,
+            actions: resolve(&effect.actions)?,
+            once_per_turn: effect.once_per_turn,
+            remains: effect.remains,
+        })
+    }
+}
+
+/// サンプリングで用いるデッキと手札．カードはIDで表し，カード名は共有する`Deck`から取得する．
+#[derive(Clone)]
+pub struct DeckInstance {
+    /// デッキの内容を表すVec．ドローの構造上一番上のカードが最後の要素となる．
+    deck_ids: Vec<CardId>,
+    hand_ids: Vec<CardId>,
+    /// 元のデッキ．カード名と効果を保持する．
+    deck: Rc<Deck>,
+    /// IDごとの代用カード(`acts_as`)のサーチ先
+    substitutes: Rc<Vec<Vec<CardId>>>,
+    /// IDごとの効果
+    effects: Rc<Vec<Vec<ResolvedEffect>>>,
+}
+
+impl DeckInstance {
+    /// O(a * n) (aはカード枚数上限)．代用カードのサーチ先や効果の対象に不正なクエリがある場合はエラー
+    pub fn new(deck: &Deck) -> Result<Self, AppError> {
+        Ok(DeckInstance {
+            deck_ids: deck.card_ids(),
+            hand_ids: Vec::new(),
+            deck: Rc::new(deck.clone()),
+            substitutes: Rc::new(deck_substitutes(deck)?),
+            effects: Rc::new(
+                deck.iter()
+                    .map(|card| {
+                        card.effects
+                            .iter()
+                            .map(|effect| ResolvedEffect::new(deck, effect))
+                            .collect()
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+
+    /// デッキをシャッフルする．O(n)．
+    pub fn shuffle(&mut self) {
+        self.deck_ids.shuffle(&mut thread_rng());
+    }
+
+    /// `draw_n`分ドローする．デッキが足りない場合は残り全てをドローする．O(draw_n)．
+    pub fn draw(&mut self, draw_n: usize) {
+        for _ in 0..draw_n {
+            let Some(draw_card) = self.deck_ids.pop() else {
+                break;
+            };
+            self.hand_ids.push(draw_card);
+        }
+    }
+
+    /// 手札の`hand_index`番目のカードをデッキの一番下に置く．O(n)．
+    pub fn put_bottom(&mut self, hand_index: usize) {
+        let bottom_card = self.hand_ids.remove(hand_index);
+        self.deck_ids.insert(0, bottom_card);
+    }
+
+    /// デッキの上から`banish_n`枚除外する．デッキが足りない場合は残り全てを除外する．O(banish_n)．
+    pub fn banish(&mut self, banish_n: usize) {
+        let deck_length = self.deck_ids.len();
+        self.deck_ids.truncate(deck_length.saturating_sub(banish_n));
+    }
+
+    /// デッキから`targets`のうち最初に見つかったカードを手札に加える．加えられた場合はtrueを返す．O(n)．
+    /// パターンの成立に必要なカードを選ぶのではなく，`targets`に書いた順に探すため，優先するカードを先に書く．
+    pub fn search(&mut self, targets: &[CardId]) -> bool {
+        for target in targets.iter() {
+            if let Some(i) = self.deck_ids.iter().position(|id| id == target) {
+                let search_card = self.deck_ids.remove(i);
+                self.hand_ids.push(search_card);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// デッキの上から`excavate_n`枚めくり，`targets`のうち最初に見つかったカードを手札に加える．
+    /// 残りはデッキの下に戻す．加えられた場合はtrueを返す．デッキが足りない場合は残り全てをめくる．O(n)．
+    /// `search`と同様に`targets`に書いた順に探す．
+    pub fn excavate(&mut self, excavate_n: usize, targets: &[CardId]) -> bool {
+        let deck_length = self.deck_ids.len();
+        let mut excavated = self
+            .deck_ids
+            .split_off(deck_length.saturating_sub(excavate_n));
+
+        let found = targets
+            .iter()
+            .find_map(|target| excavated.iter().position(|id| id == target));
+        if let Some(i) = found {
+            let excavate_card = excavated.remove(i);
+            self.hand_ids.push(excavate_card);
+        }
+
+        // 残りはデッキの下(先頭)に戻す
+        excavated.append(&mut self.deck_ids);
+        self.deck_ids = excavated;
+
+        found.is_some()
+    }
+
+    /// 効果を発動できるかどうか．コストと効果処理に必要なデッキ枚数と，サーチ先の存在を確認する．
+    fn can_activate(&self, effect: &ResolvedEffect) -> bool {
+        let required_deck_n = effect
+            .cost
+            .iter()
+            .chain(effect.actions.iter())
+            .map(|resolved| resolved.action.required_deck_n())
+            .sum::<usize>();
+
+        let searchable = effect.actions.iter().all(|resolved| match resolved.action {
+            EffectAction::Search { .. } => {
+                self.deck_ids.iter().any(|id| resolved.targets.contains(id))
+            }
+            _ => true,
+        });
+
+        self.deck_ids.len() >= required_deck_n && searchable
+    }
+
+    /// 一つの動作を処理する．
+    fn resolve_action(&mut self, resolved: &ResolvedAction) {
+        match resolved.action {
+            EffectAction::Draw { number } => self.draw(number as usize),
+            EffectAction::Banish { number } => self.banish(number as usize),
+            EffectAction::Search { .. } => {
+                self.search(&resolved.targets);
+            }
+            EffectAction::Excavate { number, .. } => {
+                self.excavate(number as usize, &resolved.targets);
+            }
+        }
+    }
+
+    /// 手札のカードの効果を手札の先頭から順に処理する．効果によって手札に加わったカードもその後に処理する．
+    /// 各カードは発動できる最初の効果を一度だけ発動し，`remains`でない場合は手札から取り除く．
+    pub fn resolve_effects(&mut self) {
+        let effects = Rc::clone(&self.effects);
+        let mut used_ids: HashSet<CardId> = HashSet::new(); // 1ターンに1度の効果を発動したカード
+        let mut i = 0;
+
+        while i < self.hand_ids.len() {
+            let card_id = self.hand_ids[i];
+            let card_effects = &effects[card_id.index()];
+
+            let effect = card_effects.iter().find(|effect| {
+                !(effect.once_per_turn && used_ids.contains(&card_id)) && self.can_activate(effect)
+            });
+
+            match effect {
+                Some(effect) => {
+                    if effect.once_per_turn {
+                        used_ids.insert(card_id);
+                    }
+
+                    if effect.remains {
+                        i += 1;
+                    } else {
+                        self.hand_ids.remove(i);
+                    }
+
+                    for action in effect.cost.iter().chain(effect.actions.iter()) {
+                        self.resolve_action(action);
+                    }
+                }
+                None => {
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    pub fn deck(&self) -> &[CardId] {
+        &self.deck_ids
+    }
+    pub fn hands(&self) -> &[CardId] {
+        &self.hand_ids
+    }
+
+    /// 手札のカード名．出力の際に用いる．
+    pub fn hand_names(&self) -> Vec<&str> {
+        self.hand_ids
+            .iter()
+            .map(|id| self.deck.name_of(*id))
+            .collect()
+    }
+}
+
+/// パターンを検証してコンパイルする．試行回数が0の場合は確率を推定できないためエラー
+fn compile_patterns(
+    deck: &Deck,
+    patterns: &[InitialPattern],
+    draw_n: usize,
+    trial_n: usize,
+) -> Result<PatternMatcher, AppError> {
+    let matcher = PatternMatcher::new(deck, patterns)?;
+
+    validate_draw_n(deck.size() as u64, draw_n as u64)?;
+    if trial_n == 0 {
+        return Err(AppError::InvalidDataError(
+            "trial_n must be positive.".to_string(),
+        ));
+    }
+
+    Ok(matcher)
+}
+
+/// 手札のカードの効果を処理した後の手札に対応したレベルを取得する．マッチしたパターンのインデックスごとに`on_match`を呼ぶ．
+/// 手札の代用カードは，全探索と同様にデッキに残っているサーチ先として扱う．
+fn resolved_level(
+    mut deck_instance: DeckInstance,
+    matcher: &PatternMatcher,
+    on_match: impl FnMut(usize),
+) -> Option<usize> {
+    deck_instance.resolve_effects();
+
+    let mut counts = vec![0; deck_instance.deck.len()];
+    fill_counts(&mut counts, deck_instance.hands());
+    // 手札とデッキに残っているカードの枚数．除外したカードは含まない．
+    let mut available = counts.clone();
+    for id in deck_instance.deck().iter() {
+        available[id.index()] += 1;
+    }
+
+    let virtual_hands = substitute_hands(&counts, &deck_instance.substitutes, &available)
+        .into_iter()
+        .map(MatchHand::new)
+        .collect::<Vec<_>>();
+    matcher.hand_level(&virtual_hands, on_match)
+}
+
+/// レベルに対応したカウンタについて，そのレベルまでの全てのレベルをインクリメント
+fn increment_level(numbers_per_level: &mut [u64], level: usize) {
+    for number in numbers_per_level.iter_mut().take(level + 1) {
+        *number += 1;
+    }
+}
+
+/// ランダムドローによってパターンの確率を推定する．手札のカードの効果を処理した後にパターンを判定する．
+/// 試行一回ごとに`progress`を進める．
+pub fn sampling_pattern(
+    deck: &Deck,
+    patterns: &[InitialPattern],
+    draw_n: usize,
+    trial_n: usize,
+    progress: &mut Progress,
+) -> Result<AllSearchResult, AppError> {
+    let deck_instance = DeckInstance::new(deck)?;
+    let matcher = compile_patterns(deck, patterns, draw_n, trial_n)?;
+
+    let mut numbers_per_pat: Vec<u64> = vec![0; patterns.len()]; // パターンにマッチした回数
+    let mut numbers_per_level: Vec<u64> = vec![0; matcher.max_level as usize + 1];
+
+    progress.start(trial_n as u64)?;
+    for _ in 0..trial_n {
+        let mut deck_instance = deck_instance.clone();
+        deck_instance.shuffle();
+        deck_instance.draw(draw_n);
+
+        let level_flag = resolved_level(deck_instance, &matcher, |pat_i| {
+            numbers_per_pat[pat_i] += 1;
+        });
+
+        if let Some(level) = level_flag {
+            increment_level(&mut numbers_per_level, level);
+        }
+        progress.advance(1)?;
+    }
+    progress.finish();
+
+    Ok(AllSearchResult {
+        pattern_result: numbers_per_pat
+            .into_iter()
+            .map(|pat_n| pat_n as f64 / trial_n as f64)
+            .collect(),
+        level_result: numbers_per_level
+            .into_iter()
+            .map(|level_n| level_n as f64 / trial_n as f64)
+            .collect(),
+    })
+}
+
+/// マリガンを含めてランダムドローによってレベルの確率を推定する．マリガンするかどうかは効果を処理した後の手札で判定する．
+/// 試行一回ごとに`progress`を進める．
+pub fn sampling_mulligan(
+    deck: &Deck,
+    patterns: &[InitialPattern],
+    draw_n: usize,
+    policy: &MulliganPolicy,
+    trial_n: usize,
+    progress: &mut Progress,
+) -> Result<MulliganResult, AppError> {
+    let deck_instance = DeckInstance::new(deck)?;
+    let matcher = compile_patterns(deck, patterns, draw_n, trial_n)?;
+
+    let bottom_n = match policy.method {
+        MulliganMethod::ShuffleBack => 0,
+        MulliganMethod::London { bottom_n } if bottom_n as usize <= draw_n => bottom_n as usize,
+        MulliganMethod::London { .. } => {
+            return Err(AppError::InvalidDataError(
+                "bottom_n is larger than draw_n.".to_string(),
+            ));
+        }
+    }; // 引き直した後にデッキの下に置く枚数
+
+    let mut numbers_per_level: Vec<u64> = vec![0; matcher.max_level as usize + 1];
+    let mut mulligan_number: u64 = 0;
+
+    progress.start(trial_n as u64)?;
+    for _ in 0..trial_n {
+        let mut first_instance = deck_instance.clone();
+        first_instance.shuffle();
+        first_instance.draw(draw_n);
+
+        let level_flag = match resolved_level(first_instance, &matcher, |_| {}) {
+            Some(level) if level >= policy.level as usize => Some(level),
+            _ => {
+                mulligan_number += 1;
+
+                // 手札を全てデッキに戻して引き直す
+                let mut redraw_instance = deck_instance.clone();
+                redraw_instance.shuffle();
+                redraw_instance.draw(draw_n);
+
+                // デッキの下に置くカードの選び方の中で最もレベルの高いもの
+                (0..draw_n)
+                    .combinations(bottom_n)
+                    .map(|bottom_indices| {
+                        let mut keep_instance = redraw_instance.clone();
+                        for i in bottom_indices.into_iter().rev() {
+                            keep_instance.put_bottom(i);
+                        }
+                        resolved_level(keep_instance, &matcher, |_| {})
+                    })
+                    .max()
+                    .flatten()
+            }
+        };
+
+        if let Some(level) = level_flag {
+            increment_level(&mut numbers_per_level, level);
+        }
+        progress.advance(1)?;
+    }
+    progress.finish();
+
+    Ok(MulliganResult {
+        level_result: numbers_per_level
+            .into_iter()
+            .map(|level_n| level_n as f64 / trial_n as f64)
+            .collect(),
+        mulligan_rate: mulligan_number as f64 / trial_n as f64,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sampling_mulligan, sampling_pattern, DeckInstance};
+    use crate::domain::{
+        Card, CardEffect, CardId, Deck, EffectAction, InitialPattern, MulliganMethod,
+        MulliganPolicy, StringOrList,
+    };
+    use crate::progress::{CancellationToken, Progress};
+    use crate::AppError;
+
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_deck_instance() {
+        let deck = Deck::new(vec![
+            Card {
+                name: "強欲な壺".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "ブラック・マジシャン".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "幻獣王ガゼル".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "暗黒騎士ガイア".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+            Card {
+                name: "エルフの剣士".to_string(),
+                number: 3,
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+
+        let mut card_name_pool = HashSet::new();
+        card_name_pool.insert("強欲な壺".to_string());
+        card_name_pool.insert("ブラック・マジシャン".to_string());
+        card_name_pool.insert("幻獣王ガゼル".to_string());
+        card_name_pool.insert("暗黒騎士ガイア".to_string());
+        card_name_pool.insert("エルフの剣士".to_string());
+
+        let mut deck_instance = DeckInstance::new(&deck).unwrap();
+        deck_instance.shuffle();
+
+        assert_eq!(deck_instance.deck().len(), 15);
+        assert_eq!(deck_instance.hands().len(), 0);
+
+        deck_instance.draw(5);
+        assert_eq!(deck_instance.deck().len(), 10);
+        assert_eq!(deck_instance.hands().len(), 5);
+
+        for card_name in deck_instance.hand_names() {
+            assert!(card_name_pool.contains(card_name));
+        }
+    }
+
+    #[test]
+    fn test_resolve_effects() {
+        // シャッフルしないため，デッキの最後のカードから順にドローする
+        let deck = Deck::new(vec![
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 1,
+                ..Default::default()
+            },
+            Card {
+                name: "灰流うらら".to_string(),
+                number: 1,
+                ..Default::default()
+            },
+            Card {
+                name: "溟界の蛇睡蓮".to_string(),
+                number: 1,
+                effects: vec![CardEffect {
+                    cost: Vec::new(),
+                    actions: vec![EffectAction::Search {
+                        targets: StringOrList::String("溟界の滓－ヌル".to_string()),
+                    }],
+                    once_per_turn: true,
+                    remains: false,
+                }],
+                ..Default::default()
+            },
+            Card {
+                name: "増殖するG".to_string(),
+                number: 10,
+                ..Default::default()
+            },
+            Card {
+                name: "強欲で貪欲な壺".to_string(),
+                number: 1,
+                effects: vec![CardEffect {
+                    cost: vec![EffectAction::Banish { number: 10 }],
+                    actions: vec![EffectAction::Draw { number: 2 }],
+                    once_per_turn: true,
+                    remains: false,
+                }],
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+
+        let mut deck_instance = DeckInstance::new(&deck).unwrap();
+        deck_instance.draw(1);
+        deck_instance.resolve_effects();
+
+        // 壺でデッキの上から10枚除外して2枚ドローし，ドローした蛇睡蓮でヌルをサーチする
+        assert_eq!(
+            deck_instance.hand_names(),
+            vec!["灰流うらら", "溟界の滓－ヌル"]
+        );
+        assert_eq!(deck_instance.deck().len(), 0);
+
+        // デッキの枚数を超えるドロー・除外・めくる枚数は残り全てを対象とする
+        let mut deck_instance = DeckInstance::new(&deck).unwrap();
+        deck_instance.draw(10);
+        deck_instance.banish(10);
+        assert_eq!(deck_instance.deck().len(), 0);
+        assert!(!deck_instance.excavate(3, &[CardId(0)]));
+        deck_instance.draw(1);
+        assert_eq!(deck_instance.hands().len(), 10);
+    }
+
+    #[test]
+    fn test_sampling_pattern() {
+        let deck = Deck::new(vec![
+            Card {
+                name: "溟界の滓－ヌル".to_string(),
+                number: 1,
+                ..Default::default()
+            },
+            Card {
+                name: "溟界の蛇睡蓮".to_string(),
+                number: 39,
+                effects: vec![CardEffect {
+                    cost: Vec::new(),
+                    actions: vec![EffectAction::Search {
+                        targets: StringOrList::String("溟界の滓－ヌル".to_string()),
+                    }],
+                    once_per_turn: true,
+                    remains: true,
+                }],
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+
+        let patterns = vec![InitialPattern {
+            name: "ヌル＋蛇睡蓮".to_string(),
+            first: StringOrList::String("溟界の滓－ヌル".to_string()),
+            second: Some(StringOrList::String("溟界の蛇睡蓮".to_string())),
+            third: None,
+            level: 1,
+            extra: None,
+        }];
+
+        let result = sampling_pattern(&deck, &patterns, 5, 100, &mut Progress::silent()).unwrap();
+        assert_eq!(result.pattern_result, vec![1.0]);
+        assert_eq!(result.level_result, vec![1.0, 1.0]);
+
+        assert!(sampling_pattern(&deck, &patterns, 5, 0, &mut Progress::silent()).is_err());
+
+        // 代用カードのサーチ先に不正なクエリがある場合
+        let invalid_deck = deck
+            .with_cards(vec![
+                deck[0].clone(),
+                Card {
+                    acts_as: Some(StringOrList::String("@color=黒".to_string())),
+                    ..deck[1].clone()
+                },
+            ])
+            .unwrap();
+        assert!(
+            sampling_pattern(&invalid_deck, &patterns, 5, 100, &mut Progress::silent()).is_err()
+        );
+
+        let token = CancellationToken::new();
+        token.cancel();
+        assert!(matches!(
+            sampling_pattern(
+                &deck,
+                &patterns,
+                5,
+                100,
+                &mut Progress::silent().with_token(token)
+            ),
+            Err(AppError::CancelledError)
+        ));
+
+        // 効果を持たない代用カードはデッキに残っているサーチ先として扱う
+        let searcher_deck = deck
+            .with_cards(vec![
+                deck[0].clone(),
+                Card {
+                    effects: Vec::new(),
+                    acts_as: Some(StringOrList::String("溟界の滓－ヌル".to_string())),
+                    ..deck[1].clone()
+                },
+            ])
+            .unwrap();
+        let result =
+            sampling_pattern(&searcher_deck, &patterns, 5, 100, &mut Progress::silent()).unwrap();
+        assert_eq!(result.pattern_result, vec![1.0]);
+    }
+
+    #[test]
+    fn test_sampling_mulligan() {
+        let patterns = vec![InitialPattern {
+            name: "ヌル".to_string(),
+            first: StringOrList::String("溟界の滓－ヌル".to_string()),
+            second: None,
+            third: None,
+            level: 1,
+            extra: None,
+        }];
+        let policy = MulliganPolicy {
+            level: 1,
+            method: MulliganMethod::London { bottom_n: 1 },
+        };
+
+        let deck = Deck::new(vec![Card {
+            name: "溟界の滓－ヌル".to_string(),
+            number: 40,
+            ..Default::default()
+        }])
+        .unwrap();
+        let result =
+            sampling_mulligan(&deck, &patterns, 5, &policy, 100, &mut Progress::silent()).unwrap();
+        assert_eq!(result.mulligan_rate, 0.0);
+        assert_eq!(result.level_result, vec![1.0, 1.0]);
+
+        let deck = Deck::new(vec![Card {
+            name: "灰流うらら".to_string(),
+            number: 40,
+            ..Default::default()
+        }])
+        .unwrap();
+        let result =
+            sampling_mulligan(&deck, &patterns, 5, &policy, 100, &mut Progress::silent()).unwrap();
+        assert_eq!(result.mulligan_rate, 1.0);
+        assert_eq!(result.level_result, vec![0.0, 0.0]);
+    }
+}